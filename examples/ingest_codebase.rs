@@ -7,7 +7,7 @@
 //! The pipeline will:
 //! - Load all `.rs` files from the current directory
 //! - Skip any nodes previously processed; hashes are based on the path and chunk (not the
-//! metadata!)
+//!   metadata!)
 //! - Run metadata QA on each chunk; generating questions and answers and adding metadata
 //! - Chunk the code into pieces of 10 to 2048 bytes
 //! - Embed the chunks in batches of 10, Metadata is embedded by default
@@ -23,7 +23,7 @@ use swiftide::{
     ingestion,
     integrations::{self, qdrant::Qdrant, redis::RedisNodeCache},
     loaders::FileLoader,
-    transformers::{ChunkCode, MetadataQACode, OpenAIEmbed},
+    transformers::{ChunkCode, GenericEmbed, MetadataQACode},
 };
 
 #[tokio::main]
@@ -55,7 +55,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             "rust",
             10..2048,
         )?)
-        .then_in_batch(10, OpenAIEmbed::new(openai_client.clone()))
+        .then_in_batch(10, GenericEmbed::new(openai_client.clone()))
         .then_store_with(
             Qdrant::try_from_url(qdrant_url)?
                 .batch_size(50)
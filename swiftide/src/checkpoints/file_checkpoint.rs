@@ -0,0 +1,160 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use tokio::{fs::File, io::AsyncWriteExt as _, sync::Mutex};
+
+use crate::{ingestion::IngestionNode, Checkpoint};
+
+/// `FileCheckpoint` records completed nodes in a local file, one node hash per line.
+///
+/// Nodes are identified by the hash of their path and content, so a file that changed since the
+/// previous run is processed again. Every line is prefixed with the hash version, and entries with
+/// a different version are ignored. Every completed node is appended and flushed immediately, so
+/// the checkpoint survives the process being killed.
+///
+/// # Example
+///
+/// ```ignore
+/// IngestionPipeline::from_loader(FileLoader::new(".").with_extensions(&["rs"]))
+///     .with_checkpoint(FileCheckpoint::try_new(".swiftide-checkpoint")?)
+///     .then_chunk(ChunkCode::try_for_language("rust")?)
+///     .then_store_with(storage)
+///     .run()
+///     .await?;
+/// ```
+#[derive(Debug)]
+pub struct FileCheckpoint {
+    path: PathBuf,
+    state: Mutex<State>,
+}
+
+/// The completed nodes and the file they are appended to, locked together so concurrent writes do
+/// not interleave.
+#[derive(Debug)]
+struct State {
+    completed: HashSet<u64>,
+    file: File,
+}
+
+impl FileCheckpoint {
+    /// Opens the checkpoint file at the given path, reading the nodes completed by previous runs.
+    /// The file is created if it does not exist, and kept open to append completed nodes to.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read, created or contains invalid lines.
+    pub fn try_new(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let completed = if path.exists() {
+            std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read checkpoint {}", path.display()))?
+                .lines()
+                .filter_map(|line| line.trim().strip_prefix(&version_prefix()))
+                .map(|hash| {
+                    hash.parse::<u64>()
+                        .with_context(|| format!("Invalid checkpoint entry: {hash}"))
+                })
+                .collect::<Result<HashSet<_>>>()?
+        } else {
+            HashSet::new()
+        };
+
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open checkpoint {}", path.display()))?;
+
+        Ok(Self {
+            path,
+            state: Mutex::new(State {
+                completed,
+                file: File::from_std(file),
+            }),
+        })
+    }
+
+    /// Returns the number of completed nodes.
+    pub async fn len(&self) -> usize {
+        self.state.lock().await.completed.len()
+    }
+
+    /// Returns `true` if no nodes have been completed.
+    pub async fn is_empty(&self) -> bool {
+        self.state.lock().await.completed.is_empty()
+    }
+}
+
+fn version_prefix() -> String {
+    format!("v{}:", IngestionNode::HASH_VERSION)
+}
+
+#[async_trait]
+impl Checkpoint for FileCheckpoint {
+    async fn is_completed(&self, node: &IngestionNode) -> Result<bool> {
+        Ok(self
+            .state
+            .lock()
+            .await
+            .completed
+            .contains(&node.calculate_hash()))
+    }
+
+    async fn mark_completed(&self, node: &IngestionNode) -> Result<()> {
+        let hash = node.calculate_hash();
+        // Hold the lock while writing, so concurrent writes do not interleave
+        let mut state = self.state.lock().await;
+        if state.completed.contains(&hash) {
+            return Ok(());
+        }
+
+        state
+            .file
+            .write_all(format!("{}{hash}\n", version_prefix()).as_bytes())
+            .await
+            .with_context(|| format!("Failed to write checkpoint {}", self.path.display()))?;
+        state.file.flush().await?;
+
+        state.completed.insert(hash);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_completed_nodes_survive_reopening() {
+        let dir = temp_dir::TempDir::new().unwrap();
+        let path = dir.child("checkpoint");
+        let node = IngestionNode {
+            path: "src/main.rs".into(),
+            chunk: "fn main() {}".to_string(),
+            ..Default::default()
+        };
+
+        let checkpoint = FileCheckpoint::try_new(&path).unwrap();
+        assert!(!checkpoint.is_completed(&node).await.unwrap());
+        checkpoint.mark_completed(&node).await.unwrap();
+        checkpoint.mark_completed(&node).await.unwrap();
+
+        let checkpoint = FileCheckpoint::try_new(&path).unwrap();
+        assert!(checkpoint.is_completed(&node).await.unwrap());
+        assert_eq!(checkpoint.len().await, 1);
+
+        std::fs::write(&path, "v0:1\n").unwrap();
+        let checkpoint = FileCheckpoint::try_new(&path).unwrap();
+        assert!(checkpoint.is_empty().await);
+
+        let changed = IngestionNode {
+            chunk: "fn main() { todo!() }".to_string(),
+            ..node
+        };
+        assert!(!checkpoint.is_completed(&changed).await.unwrap());
+    }
+}
@@ -0,0 +1,9 @@
+//! The `checkpoints` module provides implementations of the `Checkpoint` trait, which allow an
+//! ingestion pipeline to resume where a previous run stopped.
+//!
+//! The `FileCheckpoint` struct records completed nodes in a local file and is re-exported for
+//! ease of use.
+
+pub mod file_checkpoint;
+
+pub use file_checkpoint::FileCheckpoint;
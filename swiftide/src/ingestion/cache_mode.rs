@@ -0,0 +1,13 @@
+//! This module defines when `filter_cached` adds nodes to the cache.
+
+/// Determines when a node that missed the cache is added to it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CacheMode {
+    /// Add the node to the cache as soon as it passes the cache. If a later stage fails, the node
+    /// is still skipped on the next run.
+    #[default]
+    Immediate,
+    /// Add the node to the cache only after every node derived from it, i.e. all of its chunks, has
+    /// been accepted by every storage backend. Nodes that fail are processed again on the next run.
+    AfterStore,
+}
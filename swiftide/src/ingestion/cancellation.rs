@@ -0,0 +1,77 @@
+//! This module defines how a running ingestion pipeline is cancelled.
+//!
+//! A pipeline can be given a `CancellationToken` with `IngestionPipeline::with_cancellation_token`.
+//! When the token is cancelled, the loader stops producing nodes and the `CancellationPolicy`
+//! decides what happens to nodes that are already in the pipeline. Either way, `run` returns the
+//! partial `RunReport` with `cancelled` set.
+//!
+//! Every stage spawns its work on tokio tasks. These tasks are aborted when the pipeline is dropped,
+//! so dropping the future returned by `run` no longer leaves work running in the background.
+
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use tokio::task::{JoinError, JoinHandle};
+
+/// Determines what happens to in-flight nodes when a pipeline is cancelled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CancellationPolicy {
+    /// Stop the loader and let all nodes already loaded finish every stage. Pending storage
+    /// batches are flushed, even if they are not full.
+    #[default]
+    Drain,
+    /// Stop the loader and abort all in-flight work. Nodes that have not been stored yet are lost.
+    Abandon,
+}
+
+/// A spawned task that is aborted when its handle is dropped.
+pub(crate) struct AbortOnDrop<T>(JoinHandle<T>);
+
+impl<T> Future for AbortOnDrop<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.0).poll(cx)
+    }
+}
+
+impl<T> Drop for AbortOnDrop<T> {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Spawns a task like `tokio::spawn`, but aborts it when the returned future is dropped.
+pub(crate) fn spawn<F>(future: F) -> AbortOnDrop<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    AbortOnDrop(tokio::spawn(future))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_dropping_aborts_task() {
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        let task = spawn(async move {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            drop(tx);
+        });
+
+        drop(task);
+
+        // The sender is dropped when the task is aborted, without waiting for the sleep
+        tokio::time::timeout(Duration::from_secs(1), rx)
+            .await
+            .expect("task was not aborted")
+            .unwrap_err();
+    }
+}
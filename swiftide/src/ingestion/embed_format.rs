@@ -0,0 +1,185 @@
+//! This module defines `EmbedFormat`, which determines the text that is embedded for a node.
+//!
+//! The same node always results in the same text, so embedding a chunk again results in the same
+//! vector. By default all metadata is included, sorted by key, followed by the chunk.
+
+use std::collections::HashSet;
+
+use super::IngestionNode;
+
+const DEFAULT_TEMPLATE: &str = "{metadata}\n{chunk}";
+
+/// Determines how a node is converted into the text that is embedded.
+///
+/// Metadata keys listed with `with_key_order` come first, in that order, followed by all other
+/// keys sorted alphabetically. Each key is formatted on its own line as `key: value`, with string
+/// values included as is and other values as JSON.
+///
+/// The template supports the placeholders `{metadata}`, `{chunk}` and `{path}`.
+///
+/// # Example
+///
+/// ```ignore
+/// let format = EmbedFormat::default()
+///     .with_excluded_keys(["Questions and Answers"])
+///     .with_template("File: {path}\n{metadata}\n\n{chunk}");
+///
+/// GenericEmbed::new(openai_client).with_embed_format(format)
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmbedFormat {
+    included_keys: Option<HashSet<String>>,
+    excluded_keys: HashSet<String>,
+    key_order: Vec<String>,
+    template: String,
+}
+
+impl Default for EmbedFormat {
+    fn default() -> Self {
+        Self {
+            included_keys: None,
+            excluded_keys: HashSet::new(),
+            key_order: Vec::new(),
+            template: DEFAULT_TEMPLATE.to_string(),
+        }
+    }
+}
+
+impl EmbedFormat {
+    /// Creates a format that embeds only the chunk, without any metadata.
+    pub fn chunk_only() -> Self {
+        Self::default().with_template("{chunk}")
+    }
+
+    /// Only includes the given metadata keys. By default all keys are included.
+    pub fn with_included_keys(mut self, keys: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.included_keys = Some(keys.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Excludes the given metadata keys.
+    pub fn with_excluded_keys(mut self, keys: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.excluded_keys = keys.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Puts the given metadata keys first, in the given order.
+    pub fn with_key_order(mut self, keys: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.key_order = keys.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets the template, with the placeholders `{metadata}`, `{chunk}` and `{path}`.
+    ///
+    /// Defaults to `"{metadata}\n{chunk}"`.
+    pub fn with_template(mut self, template: impl Into<String>) -> Self {
+        self.template = template.into();
+        self
+    }
+
+    /// Formats the node into the text to embed.
+    pub fn format(&self, node: &IngestionNode) -> String {
+        let mut output = String::with_capacity(self.template.len() + node.chunk.len());
+        let mut rest = self.template.as_str();
+
+        // Placeholders are replaced in a single pass, so placeholders in the values are kept as is
+        while let Some(start) = rest.find('{') {
+            output.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            let placeholder = rest.find('}').map(|end| &rest[..=end]);
+            match placeholder {
+                Some("{metadata}") => output.push_str(&self.format_metadata(node)),
+                Some("{chunk}") => output.push_str(&node.chunk),
+                Some("{path}") => output.push_str(&node.path.to_string_lossy()),
+                _ => {
+                    output.push('{');
+                    rest = &rest[1..];
+                    continue;
+                }
+            }
+            rest = &rest[placeholder.map_or(0, str::len)..];
+        }
+        output.push_str(rest);
+
+        output
+    }
+
+    fn format_metadata(&self, node: &IngestionNode) -> String {
+        let mut keys = node
+            .metadata
+            .keys()
+            .filter(|key| {
+                self.included_keys
+                    .as_ref()
+                    .is_none_or(|included| included.contains(*key))
+                    && !self.excluded_keys.contains(*key)
+            })
+            .collect::<Vec<_>>();
+        keys.sort_by_key(|key| {
+            (
+                self.key_order
+                    .iter()
+                    .position(|ordered| ordered == *key)
+                    .unwrap_or(usize::MAX),
+                *key,
+            )
+        });
+
+        keys.into_iter()
+            .map(|key| match &node.metadata[key] {
+                serde_json::Value::String(value) => format!("{}: {}", key, value),
+                value => format!("{}: {}", key, value),
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn node() -> IngestionNode {
+        IngestionNode {
+            path: "src/main.rs".into(),
+            chunk: "fn main() {}".to_string(),
+            metadata: HashMap::from([
+                ("b".to_string(), "two".into()),
+                ("a".to_string(), 1.into()),
+                ("c".to_string(), "three".into()),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_default_sorts_keys() {
+        assert_eq!(
+            EmbedFormat::default().format(&node()),
+            "a: 1\nb: two\nc: three\nfn main() {}"
+        );
+    }
+
+    #[test]
+    fn test_chunk_only() {
+        assert_eq!(EmbedFormat::chunk_only().format(&node()), "fn main() {}");
+    }
+
+    #[test]
+    fn test_keys_and_template() {
+        let format = EmbedFormat::default()
+            .with_excluded_keys(["a"])
+            .with_key_order(["c"])
+            .with_template("{path} {unknown}\n{metadata}\n\n{chunk} {");
+
+        assert_eq!(
+            format.format(&node()),
+            "src/main.rs {unknown}\nc: three\nb: two\n\nfn main() {} {"
+        );
+
+        let format = EmbedFormat::default().with_included_keys(["b"]);
+        assert_eq!(format.format(&node()), "b: two\nfn main() {}");
+    }
+}
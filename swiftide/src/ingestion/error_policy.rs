@@ -0,0 +1,190 @@
+//! This module defines how the ingestion pipeline handles errors.
+//!
+//! By default a single error anywhere in the pipeline aborts the run. With an `ErrorPolicy` a
+//! pipeline can instead skip failing nodes, optionally recording them in a `DeadLetterQueue`
+//! together with the stage that failed and the error, so they can be inspected after the run.
+
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use futures_util::{future, StreamExt};
+
+use super::{IngestionNode, IngestionStream, NodeError};
+
+/// Determines what happens when a stage of the pipeline returns an error.
+#[derive(Debug, Clone, Default)]
+pub enum ErrorPolicy {
+    /// Abort the pipeline on the first error.
+    #[default]
+    FailFast,
+    /// Log the error and skip the failing node.
+    SkipAndLog,
+    /// Log the error, skip the failing node and record it in the dead letter queue.
+    DeadLetter(DeadLetterQueue),
+}
+
+/// A node that failed in a stage of the pipeline, together with the error.
+#[derive(Debug)]
+pub struct DeadLetter {
+    /// Name of the stage that failed.
+    pub stage: String,
+    /// The nodes the stage was processing. Batch stages record the whole batch, or only the nodes
+    /// with the path of a `NodeError`. Loaders record no nodes.
+    pub nodes: Vec<IngestionNode>,
+    /// The error returned by the stage.
+    pub error: anyhow::Error,
+}
+
+/// Collects dead letters of a pipeline run.
+///
+/// The queue is cheap to clone and all clones share the same letters, so a clone can be passed to
+/// the pipeline and the original inspected after the run.
+#[derive(Debug, Clone, Default)]
+pub struct DeadLetterQueue {
+    letters: Arc<Mutex<Vec<DeadLetter>>>,
+}
+
+impl DeadLetterQueue {
+    /// Creates a new, empty `DeadLetterQueue`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a dead letter to the queue.
+    pub fn push(&self, letter: DeadLetter) {
+        self.lock().push(letter);
+    }
+
+    /// Returns the number of dead letters in the queue.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns `true` if the queue contains no dead letters.
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Removes and returns all dead letters in the queue.
+    pub fn take(&self) -> Vec<DeadLetter> {
+        std::mem::take(&mut *self.lock())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<DeadLetter>> {
+        // A panic while holding the lock cannot leave the letters in an inconsistent state
+        self.letters
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+impl ErrorPolicy {
+    /// Returns `true` if failing nodes are recorded, so stages need to keep a copy of their input.
+    pub(crate) fn keeps_nodes(&self) -> bool {
+        matches!(self, ErrorPolicy::DeadLetter(_))
+    }
+
+    /// Handles an error of a stage.
+    ///
+    /// Returns the error if the pipeline should abort, or `Ok(())` if the failing nodes are skipped.
+    pub(crate) fn handle(
+        &self,
+        stage: &str,
+        nodes: Vec<IngestionNode>,
+        error: anyhow::Error,
+    ) -> Result<()> {
+        match self {
+            ErrorPolicy::FailFast => Err(error),
+            ErrorPolicy::SkipAndLog => {
+                tracing::warn!(stage, error = ?error, "Skipping failed node");
+                Ok(())
+            }
+            ErrorPolicy::DeadLetter(queue) => {
+                tracing::warn!(stage, error = ?error, "Moving failed node to dead letter queue");
+                queue.push(DeadLetter {
+                    stage: stage.to_string(),
+                    nodes,
+                    error,
+                });
+                Ok(())
+            }
+        }
+    }
+
+    /// Handles all errors in a stream produced by a stage for the given nodes.
+    ///
+    /// A `NodeError` is recorded with the nodes of its path only.
+    pub(crate) fn handle_stream(
+        &self,
+        stage: &str,
+        nodes: Vec<IngestionNode>,
+        stream: IngestionStream,
+    ) -> IngestionStream {
+        if let ErrorPolicy::FailFast = self {
+            return stream;
+        }
+
+        let policy = self.clone();
+        let stage = stage.to_string();
+        stream
+            .filter_map(move |result| {
+                future::ready(match result {
+                    Ok(node) => Some(Ok(node)),
+                    Err(error) => {
+                        let nodes = match NodeError::path_of(&error) {
+                            Some(path) => nodes
+                                .iter()
+                                .filter(|node| node.path == path)
+                                .cloned()
+                                .collect(),
+                            None => nodes.clone(),
+                        };
+                        policy.handle(&stage, nodes, error).err().map(Err)
+                    }
+                })
+            })
+            .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::stream;
+
+    #[tokio::test]
+    async fn test_dead_letters_are_recorded() {
+        let queue = DeadLetterQueue::new();
+        let policy = ErrorPolicy::DeadLetter(queue.clone());
+
+        let results = policy
+            .handle_stream(
+                "stage",
+                vec![IngestionNode::default()],
+                stream::iter(vec![
+                    Ok(IngestionNode::default()),
+                    Err(anyhow::anyhow!("failed")),
+                ])
+                .boxed(),
+            )
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(results.len(), 1);
+        assert!(results[0].is_ok());
+
+        let letters = queue.take();
+        assert_eq!(letters.len(), 1);
+        assert_eq!(letters[0].stage, "stage");
+        assert_eq!(letters[0].nodes.len(), 1);
+        assert_eq!(letters[0].error.to_string(), "failed");
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_fail_fast_returns_error() {
+        let result = ErrorPolicy::FailFast.handle("stage", vec![], anyhow::anyhow!("failed"));
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,112 @@
+//! This module defines the events emitted while an ingestion pipeline runs.
+//!
+//! Handlers registered with `IngestionPipeline::on_event` are called for every node that passes a
+//! stage, every stored batch, every skipped node and every error, and once when the run finishes
+//! or fails. This makes it possible to drive progress bars or live dashboards without scraping
+//! tracing output.
+
+use std::{
+    path::PathBuf,
+    sync::{Arc, PoisonError, RwLock},
+};
+
+use super::{RunReport, StageKind};
+
+/// An event emitted by a running ingestion pipeline.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum PipelineEvent {
+    /// The loader emitted a node.
+    NodeLoaded {
+        /// Name of the loader.
+        stage: String,
+        /// Path of the loaded node.
+        path: PathBuf,
+    },
+    /// A cache or checkpoint skipped a node.
+    NodeSkipped {
+        /// Name of the cache or checkpoint.
+        stage: String,
+        /// The kind of the stage.
+        kind: StageKind,
+        /// Path of the skipped node.
+        path: PathBuf,
+    },
+    /// A cache, checkpoint, transformer or chunker passed on a node.
+    NodeProcessed {
+        /// Name of the stage.
+        stage: String,
+        /// The kind of the stage.
+        kind: StageKind,
+        /// Path of the node.
+        path: PathBuf,
+    },
+    /// A storage backend stored a node.
+    NodeStored {
+        /// Name of the storage backend.
+        stage: String,
+        /// Path of the stored node.
+        path: PathBuf,
+    },
+    /// A storage backend stored a batch of nodes, after a `NodeStored` event for each of them.
+    BatchStored {
+        /// Name of the storage backend.
+        stage: String,
+        /// Number of nodes stored in the batch.
+        count: usize,
+    },
+    /// A stage returned an error.
+    Error {
+        /// Name of the stage.
+        stage: String,
+        /// The kind of the stage.
+        kind: StageKind,
+        /// The error, including its context.
+        message: String,
+    },
+    /// The pipeline finished, successfully or cancelled.
+    Finished(RunReport),
+    /// The pipeline stopped on an error, which `IngestionPipeline::run` returns.
+    Failed {
+        /// Statistics of the run until the error.
+        report: RunReport,
+        /// The error, including its context.
+        message: String,
+    },
+}
+
+type EventHandler = Arc<dyn Fn(&PipelineEvent) + Send + Sync>;
+
+/// The event handlers of a pipeline, shared by all of its stages.
+#[derive(Default)]
+pub(crate) struct EventHandlers {
+    handlers: RwLock<Vec<EventHandler>>,
+}
+
+impl EventHandlers {
+    pub(crate) fn register(&self, handler: impl Fn(&PipelineEvent) + Send + Sync + 'static) {
+        self.handlers
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(Arc::new(handler));
+    }
+
+    /// Calls all handlers with the event. The event is only created if there are handlers.
+    pub(crate) fn emit(&self, event: impl FnOnce() -> PipelineEvent) {
+        let handlers = self.handlers.read().unwrap_or_else(PoisonError::into_inner);
+        if handlers.is_empty() {
+            return;
+        }
+
+        let event = event();
+        for handler in handlers.iter() {
+            handler(&event);
+        }
+    }
+}
+
+impl std::fmt::Debug for EventHandlers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventHandlers").finish_non_exhaustive()
+    }
+}
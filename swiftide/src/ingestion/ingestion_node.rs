@@ -10,7 +10,7 @@
 //! file path, data chunk, optional vector representation, and metadata.
 //!
 //! The struct provides methods to convert the node into an embeddable string format and to
-//! calculate a hash value for the node based on its path and chunk.
+//! calculate a stable hash value for the node based on its path and chunk.
 //!
 //! # Usage
 //!
@@ -20,15 +20,22 @@
 use std::{
     collections::HashMap,
     hash::{Hash, Hasher},
+    ops::Range,
     path::PathBuf,
 };
 
+use super::EmbedFormat;
+
 /// Represents a unit of data in the ingestion process.
 ///
 /// `IngestionNode` encapsulates all necessary information for a single unit of data being processed
 /// in the ingestion pipeline. It includes fields for an identifier, file path, data chunk, optional
 /// vector representation, and metadata.
-#[derive(Debug, Default, Clone)]
+///
+/// Nodes can be serialized with serde, i.e. to snapshot the output of a pipeline stage with
+/// `JsonlStorage` and replay it later with `JsonlLoader`. Missing fields are set to their defaults.
+#[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 pub struct IngestionNode {
     /// Optional identifier for the node.
     pub id: Option<u64>,
@@ -37,52 +44,287 @@ pub struct IngestionNode {
     /// Data chunk contained in the node.
     pub chunk: String,
     /// Optional vector representation of the data chunk.
+    ///
+    /// A node has either an unnamed vector or named `vectors`, never both. Neither takes
+    /// precedence: storage rejects a node that sets both.
     pub vector: Option<Vec<f32>>,
+    /// Named vector representations, i.e. of the chunk and of its metadata, or from different
+    /// embedding models.
+    ///
+    /// Mutually exclusive with `vector`, sparse vectors can be combined with either.
+    pub vectors: HashMap<String, Vec<f32>>,
+    /// Named sparse vector representations, i.e. term weights for hybrid search.
+    pub sparse_vectors: HashMap<String, SparseVector>,
     /// Metadata associated with the node.
-    pub metadata: HashMap<String, String>,
+    ///
+    /// Values are typed, so numbers, booleans, lists and objects are stored as such and can be
+    /// filtered on at query time.
+    pub metadata: HashMap<String, serde_json::Value>,
+    /// Where the chunk is located in the original document, if it was created by a chunker.
+    pub source_range: Option<SourceRange>,
+    /// Links the node to the node it was chunked from, if it was created by a chunker.
+    pub lineage: Option<Lineage>,
+}
+
+/// Links a chunk to the node it was chunked from and to its siblings.
+///
+/// Set by `IngestionPipeline::then_chunk`, so retrieval can expand a chunk to its neighbouring
+/// chunks or to all chunks of the parent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct Lineage {
+    /// The hash of the parent node, as calculated by `calculate_hash`.
+    pub parent_id: u64,
+    /// The position of the chunk among the chunks of the parent, starting at 0.
+    pub chunk_index: usize,
+    /// The number of chunks created from the parent, including this one.
+    pub sibling_count: usize,
+}
+
+/// A sparse vector, with the values of the non-zero dimensions only.
+#[derive(Debug, Default, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SparseVector {
+    /// The dimensions with a value, unique and sorted ascending.
+    pub indices: Vec<u32>,
+    /// The value of each dimension in `indices`.
+    pub values: Vec<f32>,
+}
+
+/// The location of a chunk in the original document.
+///
+/// Chunkers set it on the nodes they create, so retrieval results can link to the exact lines
+/// they came from, i.e. `src/main.rs#L10-L42`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct SourceRange {
+    /// Byte range of the chunk in the original document.
+    pub bytes: Range<usize>,
+    /// First line of the chunk, starting at 1.
+    pub start_line: usize,
+    /// Last line of the chunk, inclusive.
+    pub end_line: usize,
+}
+
+impl SourceRange {
+    /// Calculates the source range of the given byte range in a document.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds or not on a char boundary of the document.
+    pub fn from_bytes(document: &str, bytes: Range<usize>) -> Self {
+        let start_line = document[..bytes.start].matches('\n').count() + 1;
+        let chunk = &document[bytes.clone()];
+        // A trailing newline ends the last line of the chunk instead of starting a new one
+        let end_line = start_line + chunk.trim_end_matches('\n').matches('\n').count();
+
+        Self {
+            bytes,
+            start_line,
+            end_line,
+        }
+    }
+
+    /// Converts a range relative to the chunk of this range into one relative to the original
+    /// document.
+    pub fn offset(&self, inner: &SourceRange) -> Self {
+        Self {
+            bytes: self.bytes.start + inner.bytes.start..self.bytes.start + inner.bytes.end,
+            start_line: self.start_line + inner.start_line - 1,
+            end_line: self.start_line + inner.end_line - 1,
+        }
+    }
 }
 
 impl IngestionNode {
+    /// The version of the hash calculated by `calculate_hash`.
+    ///
+    /// Integrations store it alongside the hash, so hashes from an incompatible version are
+    /// recognized instead of silently mismatching.
+    pub const HASH_VERSION: u32 = 1;
+
     /// Converts the node into an embeddable string format.
     ///
-    /// The embeddable format consists of the metadata formatted as key-value pairs, each on a new line,
-    /// followed by the data chunk.
+    /// The embeddable format consists of the metadata formatted as key-value pairs sorted by key,
+    /// each on a new line, followed by the data chunk. String values are included as is, other
+    /// values as JSON. Use `EmbedFormat` to configure the format.
     ///
     /// # Returns
     ///
     /// A string representing the embeddable format of the node.
     pub fn as_embeddable(&self) -> String {
-        // Metadata formatted by newlines joined with the chunk
-        let metadata = self
-            .metadata
-            .iter()
-            .map(|(k, v)| format!("{}: {}", k, v))
-            .collect::<Vec<String>>()
-            .join("\n");
-
-        format!("{}\n{}", metadata, self.chunk)
+        EmbedFormat::default().format(self)
     }
 
-    /// Calculates a hash value for the node based on its path and chunk.
+    /// Calculates a stable hash value for the node based on its path and chunk.
     ///
-    /// The hash value is calculated using the default hasher provided by the standard library.
+    /// The hash is used as cache key and storage id, so it must not change between runs, platforms
+    /// or Rust releases. It is calculated with 64-bit FNV-1a over the raw bytes of the path and
+    /// chunk, instead of the standard library hasher whose output is not guaranteed to be stable.
+    /// Whenever the output of this function changes, `HASH_VERSION` is incremented.
     ///
     /// # Returns
     ///
     /// A 64-bit hash value representing the node.
     pub fn calculate_hash(&self) -> u64 {
-        let mut hasher = std::collections::hash_map::DefaultHasher::new();
-        self.hash(&mut hasher);
+        let path = self.path.to_string_lossy();
+        let mut hasher = StableHasher::default();
+
+        // Length prefixes keep the boundary between path and chunk unambiguous
+        for part in [path.as_bytes(), self.chunk.as_bytes()] {
+            hasher.write(&(part.len() as u64).to_le_bytes());
+            hasher.write(part);
+        }
         hasher.finish()
     }
+
+    /// Creates a new node for a part of the chunk of this node, as chunkers do.
+    ///
+    /// The source range of the new node is relative to the original document, so chunking a node
+    /// that was already chunked keeps the positions correct.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The byte range of the part within the chunk of this node.
+    pub fn with_subchunk(&self, bytes: Range<usize>) -> Self {
+        let range = SourceRange::from_bytes(&self.chunk, bytes.clone());
+
+        Self {
+            chunk: self.chunk[bytes].to_string(),
+            source_range: Some(match &self.source_range {
+                Some(parent) => parent.offset(&range),
+                None => range,
+            }),
+            ..self.clone()
+        }
+    }
+}
+
+/// 64-bit FNV-1a, a simple hash with a fixed specification.
+pub(crate) struct StableHasher(u64);
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl StableHasher {
+    pub(crate) fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    pub(crate) fn finish(&self) -> u64 {
+        self.0
+    }
 }
 
 impl Hash for IngestionNode {
     /// Hashes the node based on its path and chunk.
     ///
-    /// This method is used by the `calculate_hash` method to generate a hash value for the node.
+    /// This allows nodes to be used in hash based collections. Use `calculate_hash` for a hash that
+    /// is stable between runs.
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.path.hash(state);
         self.chunk.hash(state);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stable_hasher_matches_fnv1a() {
+        let mut hasher = StableHasher::default();
+        assert_eq!(hasher.finish(), 0xcbf2_9ce4_8422_2325);
+
+        hasher.write(b"a");
+        assert_eq!(hasher.finish(), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    /// Changing this value invalidates all caches and stored points, so increment
+    /// `IngestionNode::HASH_VERSION` when it changes.
+    #[test]
+    fn test_hash_is_stable() {
+        let node = IngestionNode {
+            path: "src/main.rs".into(),
+            chunk: "fn main() {}".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(IngestionNode::HASH_VERSION, 1);
+        assert_eq!(node.calculate_hash(), 10_912_920_195_953_471_307);
+    }
+
+    #[test]
+    fn test_embeddable_formats_typed_metadata() {
+        let node = IngestionNode {
+            chunk: "fn main() {}".to_string(),
+            metadata: HashMap::from([("tags".to_string(), serde_json::json!(["rust", "main"]))]),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            node.as_embeddable(),
+            "tags: [\"rust\",\"main\"]\nfn main() {}"
+        );
+    }
+
+    #[test]
+    fn test_hash_separates_path_and_chunk() {
+        let a = IngestionNode {
+            path: "ab".into(),
+            chunk: "c".to_string(),
+            ..Default::default()
+        };
+        let b = IngestionNode {
+            path: "a".into(),
+            chunk: "bc".to_string(),
+            ..Default::default()
+        };
+
+        assert_ne!(a.calculate_hash(), b.calculate_hash());
+    }
+
+    #[test]
+    fn test_source_range_lines() {
+        let document = "one\ntwo\nthree\n";
+
+        assert_eq!(
+            SourceRange::from_bytes(document, 0..3),
+            SourceRange {
+                bytes: 0..3,
+                start_line: 1,
+                end_line: 1
+            }
+        );
+        let range = SourceRange::from_bytes(document, 4..14);
+        assert_eq!((range.start_line, range.end_line), (2, 3));
+    }
+
+    #[test]
+    fn test_subchunk_is_relative_to_original_document() {
+        let node = IngestionNode {
+            path: "README.md".into(),
+            chunk: "# Title\n\nfirst\nsecond\n".to_string(),
+            ..Default::default()
+        };
+
+        let section = node.with_subchunk(9..22);
+        assert_eq!(section.chunk, "first\nsecond\n");
+        assert_eq!(section.path, node.path);
+
+        let line = section.with_subchunk(6..12);
+        assert_eq!(line.chunk, "second");
+        assert_eq!(
+            line.source_range,
+            Some(SourceRange {
+                bytes: 15..21,
+                start_line: 4,
+                end_line: 4
+            })
+        );
+        assert_eq!(&node.chunk[15..21], "second");
+    }
+}
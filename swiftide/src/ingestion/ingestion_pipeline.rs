@@ -1,10 +1,20 @@
-use crate::{BatchableTransformer, ChunkerTransformer, Loader, NodeCache, Persist, Transformer};
+use crate::{
+    BatchableTransformer, Checkpoint, ChunkerTransformer, Loader, NodeCache, Persist, Transformer,
+};
 use anyhow::Result;
-use futures_util::{StreamExt, TryFutureExt, TryStreamExt};
+use futures_util::{future, stream, FutureExt, StreamExt, TryFutureExt, TryStreamExt};
 
-use std::sync::Arc;
+use std::{sync::Arc, time::Instant};
+use tokio_util::sync::CancellationToken;
 
-use super::IngestionStream;
+use super::{
+    cancellation,
+    events::EventHandlers,
+    node_tracker::NodeTracker,
+    run_report::{stage_name, StageStats},
+    CacheMode, CancellationPolicy, ErrorPolicy, IngestionNode, IngestionStream, Lineage,
+    PipelineEvent, RunReport, StageKind,
+};
 
 /// A pipeline for ingesting files, adding metadata, chunking, transforming, embedding, and then storing them.
 ///
@@ -16,10 +26,28 @@ use super::IngestionStream;
 /// * `stream` - The stream of `IngestionNode` items to be processed.
 /// * `storage` - Optional storage backend where the processed nodes will be stored.
 /// * `concurrency` - The level of concurrency for processing nodes.
+/// * `stages` - Statistics of every stage, collected into a `RunReport` when the pipeline runs.
+/// * `error_policy` - How errors are handled in stages added from now on.
+/// * `loader_error_policy_applied` - Whether the error policy has been applied to the loader.
+/// * `cache_mode` - When caches added from now on mark nodes as cached.
+/// * `cancellation_token` - Cancels the pipeline when triggered.
+/// * `cancellation_policy` - What happens to in-flight nodes when the pipeline is cancelled.
+/// * `stop_loader` - Stops the loader, triggered when the pipeline is cancelled.
+/// * `tracker` - Tracks nodes through the stages, to act once all nodes of a source are stored.
+/// * `events` - Handlers for the events emitted by the stages.
 pub struct IngestionPipeline {
     stream: IngestionStream,
     storage: Vec<Arc<dyn Persist>>,
     concurrency: usize,
+    stages: Vec<Arc<StageStats>>,
+    error_policy: ErrorPolicy,
+    loader_error_policy_applied: bool,
+    cache_mode: CacheMode,
+    cancellation_token: CancellationToken,
+    cancellation_policy: CancellationPolicy,
+    stop_loader: CancellationToken,
+    tracker: Arc<NodeTracker>,
+    events: Arc<EventHandlers>,
 }
 
 impl Default for IngestionPipeline {
@@ -29,6 +57,15 @@ impl Default for IngestionPipeline {
             stream: Box::pin(futures_util::stream::empty()),
             storage: Default::default(),
             concurrency: num_cpus::get(),
+            stages: Default::default(),
+            error_policy: Default::default(),
+            loader_error_policy_applied: true,
+            cache_mode: CacheMode::default(),
+            cancellation_token: CancellationToken::new(),
+            cancellation_policy: CancellationPolicy::default(),
+            stop_loader: CancellationToken::new(),
+            tracker: Default::default(),
+            events: Default::default(),
         }
     }
 }
@@ -43,10 +80,40 @@ impl IngestionPipeline {
     /// # Returns
     ///
     /// An instance of `IngestionPipeline` initialized with the provided loader.
-    pub fn from_loader(loader: impl Loader + 'static) -> Self {
-        let stream = loader.into_stream();
+    pub fn from_loader<L: Loader + 'static>(loader: L) -> Self {
+        let events = Arc::new(EventHandlers::default());
+        let stats = StageStats::new(StageKind::Loader, stage_name::<L>(), Arc::clone(&events));
+        let tracker = Arc::new(NodeTracker::default());
+        let mut stream = loader.into_stream();
+
+        // Loaders do their work while being polled, so time the polls
+        let stream = {
+            let stats = Arc::clone(&stats);
+            let tracker = Arc::clone(&tracker);
+            stream::poll_fn(move |cx| {
+                let start = Instant::now();
+                let poll = stream.poll_next_unpin(cx);
+                stats.record_task(start);
+                if let std::task::Poll::Ready(Some(result)) = &poll {
+                    stats.record(result);
+                    if let Ok(node) = result {
+                        tracker.track(&node.path);
+                    }
+                }
+                poll
+            })
+        };
+
+        let stop_loader = CancellationToken::new();
         Self {
-            stream: stream.boxed(),
+            stream: stream
+                .take_until(stop_loader.clone().cancelled_owned())
+                .boxed(),
+            stages: vec![stats],
+            loader_error_policy_applied: false,
+            stop_loader,
+            tracker,
+            events,
             ..Default::default()
         }
     }
@@ -65,8 +132,177 @@ impl IngestionPipeline {
         self
     }
 
+    /// Sets the error policy for the stages added after this call.
+    ///
+    /// By default the pipeline fails on the first error. When set before any other stage is added,
+    /// the policy also applies to errors from the loader. Calling this again between stages
+    /// allows a different policy per stage.
+    ///
+    /// # Arguments
+    ///
+    /// * `error_policy` - The `ErrorPolicy` for the next stages.
+    ///
+    /// # Returns
+    ///
+    /// An instance of `IngestionPipeline` with the updated error policy.
+    pub fn with_error_policy(mut self, error_policy: ErrorPolicy) -> Self {
+        self.error_policy = error_policy;
+        self
+    }
+
+    /// Sets when caches added after this call mark nodes as cached.
+    ///
+    /// By default nodes are cached as soon as they pass the cache. With `CacheMode::AfterStore`,
+    /// nodes are cached only once all nodes derived from them have been stored, so nodes that fail
+    /// in a later stage are processed again on the next run.
+    ///
+    /// # Arguments
+    ///
+    /// * `cache_mode` - The `CacheMode` for the next caches.
+    ///
+    /// # Returns
+    ///
+    /// An instance of `IngestionPipeline` with the updated cache mode.
+    pub fn with_cache_mode(mut self, cache_mode: CacheMode) -> Self {
+        self.cache_mode = cache_mode;
+        self
+    }
+
+    /// Registers a handler for the events emitted while the pipeline runs.
+    ///
+    /// Handlers are called from the tasks of the stages, so they should return quickly. To process
+    /// events elsewhere, i.e. to update a progress bar, send them to a channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `handler` - A function called with every `PipelineEvent`.
+    ///
+    /// # Returns
+    ///
+    /// An instance of `IngestionPipeline` that calls the handler for every event.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    ///
+    /// IngestionPipeline::from_loader(loader)
+    ///     .on_event(move |event| {
+    ///         let _ = tx.send(event.clone());
+    ///     })
+    /// ```
+    pub fn on_event(self, handler: impl Fn(&PipelineEvent) + Send + Sync + 'static) -> Self {
+        self.events.register(handler);
+        self
+    }
+
+    /// Sets a token to cancel the pipeline with.
+    ///
+    /// When the token is cancelled, the loader stops and in-flight nodes are handled according to
+    /// the `CancellationPolicy`. The run then finishes with a partial `RunReport`.
+    ///
+    /// # Arguments
+    ///
+    /// * `cancellation_token` - The `CancellationToken` to cancel the pipeline with.
+    ///
+    /// # Returns
+    ///
+    /// An instance of `IngestionPipeline` that can be cancelled with the token.
+    pub fn with_cancellation_token(mut self, cancellation_token: CancellationToken) -> Self {
+        self.cancellation_token = cancellation_token;
+        self
+    }
+
+    /// Sets what happens to in-flight nodes when the pipeline is cancelled.
+    ///
+    /// By default in-flight nodes are drained through all stages.
+    ///
+    /// # Arguments
+    ///
+    /// * `cancellation_policy` - The `CancellationPolicy` to apply on cancellation.
+    ///
+    /// # Returns
+    ///
+    /// An instance of `IngestionPipeline` with the updated cancellation policy.
+    pub fn with_cancellation_policy(mut self, cancellation_policy: CancellationPolicy) -> Self {
+        self.cancellation_policy = cancellation_policy;
+        self
+    }
+
+    /// Skips nodes completed in a previous run and records nodes completed in this run.
+    ///
+    /// A node is recorded as completed only after every node derived from it, i.e. all of its
+    /// chunks, has been accepted by every storage backend. If any of them fails, the node is not
+    /// recorded and will be processed again on the next run. Nodes are related to the nodes derived
+    /// from them by their path, so this stage is best added directly after the loader.
+    ///
+    /// # Arguments
+    ///
+    /// * `checkpoint` - A checkpoint that implements the `Checkpoint` trait.
+    ///
+    /// # Returns
+    ///
+    /// An instance of `IngestionPipeline` with the updated stream that skips completed nodes.
+    pub fn with_checkpoint<C: Checkpoint + 'static>(mut self, checkpoint: C) -> Self {
+        let checkpoint = Arc::new(checkpoint);
+        let stats = self.add_stage::<C>(StageKind::Checkpoint);
+        let error_policy = self.error_policy.clone();
+        let tracker = Arc::clone(&self.tracker);
+        self.stream = self
+            .stream
+            .try_filter_map(move |node| {
+                let checkpoint = Arc::clone(&checkpoint);
+                let stats = Arc::clone(&stats);
+                let error_policy = error_policy.clone();
+                let tracker = Arc::clone(&tracker);
+                let current_span = tracing::Span::current();
+                cancellation::spawn(current_span.in_scope(|| async move {
+                    let completed = stats.timed(checkpoint.is_completed(&node)).await;
+                    if let Ok(true) = completed {
+                        tracing::debug!("Node completed in a previous run, skipping");
+                        stats.record_skipped(&node);
+                        tracker.done(&node.path);
+                        return Ok(None);
+                    }
+
+                    if let Err(error) = completed {
+                        stats.record_error(&error);
+                        tracker.fail(&node.path);
+                        tracker.done(&node.path);
+                        return error_policy
+                            .handle(stats.name(), vec![node], error)
+                            .map(|()| None);
+                    }
+
+                    stats.record_node(&node);
+                    let completed = node.clone();
+                    tracker.on_completed(
+                        &node.path,
+                        Box::new(move || {
+                            async move {
+                                match checkpoint.mark_completed(&completed).await {
+                                    Ok(()) => Ok(()),
+                                    Err(error) => {
+                                        error_policy.handle(stats.name(), vec![completed], error)
+                                    }
+                                }
+                            }
+                            .boxed()
+                        }),
+                    );
+                    Ok(Some(node))
+                }))
+                .map_err(anyhow::Error::from)
+                .and_then(future::ready)
+            })
+            .boxed();
+        self
+    }
+
     /// Filters out cached nodes using the provided cache.
     ///
+    /// When nodes are added to the cache depends on the `CacheMode` set with `with_cache_mode`.
+    ///
     /// # Arguments
     ///
     /// * `cache` - A cache that implements the `NodeCache` trait.
@@ -74,22 +310,48 @@ impl IngestionPipeline {
     /// # Returns
     ///
     /// An instance of `IngestionPipeline` with the updated stream that filters out cached nodes.
-    pub fn filter_cached(mut self, cache: impl NodeCache + 'static) -> Self {
+    pub fn filter_cached<C: NodeCache + 'static>(mut self, cache: C) -> Self {
         let cache = Arc::new(cache);
+        let stats = self.add_stage::<C>(StageKind::Cache);
+        let tracker = Arc::clone(&self.tracker);
+        let cache_mode = self.cache_mode;
         self.stream = self
             .stream
             .try_filter_map(move |node| {
                 let cache = Arc::clone(&cache);
+                let stats = Arc::clone(&stats);
+                let tracker = Arc::clone(&tracker);
                 let current_span = tracing::Span::current();
-                tokio::spawn(current_span.in_scope(|| async move {
-                    if !cache.get(&node).await {
-                        cache.set(&node).await;
+                cancellation::spawn(current_span.in_scope(|| async move {
+                    let start = Instant::now();
+                    let result = if !cache.get(&node).await {
+                        match cache_mode {
+                            CacheMode::Immediate => cache.set(&node).await,
+                            CacheMode::AfterStore => {
+                                let cached = node.clone();
+                                tracker.on_completed(
+                                    &node.path,
+                                    Box::new(move || {
+                                        async move {
+                                            cache.set(&cached).await;
+                                            Ok(())
+                                        }
+                                        .boxed()
+                                    }),
+                                );
+                            }
+                        }
                         tracing::debug!("Node not in cache, passing through");
+                        stats.record_node(&node);
                         Some(node)
                     } else {
                         tracing::debug!("Node in cache, skipping");
+                        stats.record_skipped(&node);
+                        tracker.done(&node.path);
                         None
-                    }
+                    };
+                    stats.record_task(start);
+                    result
                 }))
                 .map_err(anyhow::Error::from)
             })
@@ -106,21 +368,41 @@ impl IngestionPipeline {
     /// # Returns
     ///
     /// An instance of `IngestionPipeline` with the updated stream that applies the transformer to each node.
-    pub fn then(mut self, transformer: impl Transformer + 'static) -> Self {
+    pub fn then<T: Transformer + 'static>(mut self, transformer: T) -> Self {
         let transformer = Arc::new(transformer);
         let concurrency = transformer.concurrency().unwrap_or(self.concurrency);
+        let stats = self.add_stage::<T>(StageKind::Transformer);
+        let error_policy = self.error_policy.clone();
+        let tracker = Arc::clone(&self.tracker);
         self.stream = self
             .stream
             .map_ok(move |node| {
                 let transformer = Arc::clone(&transformer);
+                let stats = Arc::clone(&stats);
+                let error_policy = error_policy.clone();
+                let tracker = Arc::clone(&tracker);
                 let current_span = tracing::Span::current();
-                tokio::spawn(
-                    current_span.in_scope(|| async move { transformer.transform_node(node).await }),
-                )
+                cancellation::spawn(current_span.in_scope(|| async move {
+                    let path = node.path.clone();
+                    let input = error_policy.keeps_nodes().then(|| node.clone());
+                    let result = stats.timed(transformer.transform_node(node)).await;
+                    stats.record(&result);
+                    match result {
+                        Ok(node) => Ok(Some(node)),
+                        Err(error) => {
+                            tracker.fail(&path);
+                            tracker.done(&path);
+                            error_policy
+                                .handle(stats.name(), input.into_iter().collect(), error)
+                                .map(|()| None)
+                        }
+                    }
+                }))
                 .map_err(anyhow::Error::from)
             })
             .try_buffer_unordered(concurrency)
             .map(|x| x.and_then(|x| x))
+            .try_filter_map(|node| async move { Ok(node) })
             .boxed();
 
         self
@@ -136,23 +418,43 @@ impl IngestionPipeline {
     /// # Returns
     ///
     /// An instance of `IngestionPipeline` with the updated stream that applies the batch transformer to each batch of nodes.
-    pub fn then_in_batch(
+    pub fn then_in_batch<T: BatchableTransformer + 'static>(
         mut self,
         batch_size: usize,
-        transformer: impl BatchableTransformer + 'static,
+        transformer: T,
     ) -> Self {
         let transformer = Arc::new(transformer);
         let concurrency = transformer.concurrency().unwrap_or(self.concurrency);
+        let stats = self.add_stage::<T>(StageKind::BatchTransformer);
+        let error_policy = self.error_policy.clone();
+        let tracker = Arc::clone(&self.tracker);
         self.stream = self
             .stream
             .try_chunks(batch_size)
             .map_ok(move |chunks| {
                 let transformer = Arc::clone(&transformer);
+                let stats = Arc::clone(&stats);
+                let error_policy = error_policy.clone();
+                let tracker = Arc::clone(&tracker);
                 let current_span = tracing::Span::current();
-                tokio::spawn(
-                    current_span
-                        .in_scope(|| async move { transformer.batch_transform(chunks).await }),
-                )
+                cancellation::spawn(current_span.in_scope(|| async move {
+                    let paths = chunks.iter().map(|node| node.path.clone()).collect();
+                    let input = if error_policy.keeps_nodes() {
+                        chunks.clone()
+                    } else {
+                        Vec::new()
+                    };
+                    let stream = stats
+                        .timed(transformer.batch_transform(chunks))
+                        .await
+                        .inspect({
+                            let stats = Arc::clone(&stats);
+                            move |result| stats.record(result)
+                        })
+                        .boxed();
+                    let stream = tracker.track_stream(paths, stream);
+                    error_policy.handle_stream(stats.name(), input, stream)
+                }))
                 .map_err(anyhow::Error::from)
             })
             .err_into::<anyhow::Error>()
@@ -164,6 +466,9 @@ impl IngestionPipeline {
 
     /// Adds a chunker transformer to the pipeline.
     ///
+    /// Every chunk gets a `Lineage` linking it to the node it was chunked from and to its
+    /// siblings.
+    ///
     /// # Arguments
     ///
     /// * `chunker` - A transformer that implements the `ChunkerTransformer` trait.
@@ -171,17 +476,36 @@ impl IngestionPipeline {
     /// # Returns
     ///
     /// An instance of `IngestionPipeline` with the updated stream that applies the chunker transformer to each node.
-    pub fn then_chunk(mut self, chunker: impl ChunkerTransformer + 'static) -> Self {
+    pub fn then_chunk<C: ChunkerTransformer + 'static>(mut self, chunker: C) -> Self {
         let chunker = Arc::new(chunker);
         let concurrency = chunker.concurrency().unwrap_or(self.concurrency);
+        let stats = self.add_stage::<C>(StageKind::Chunker);
+        let error_policy = self.error_policy.clone();
+        let tracker = Arc::clone(&self.tracker);
         self.stream = self
             .stream
             .map_ok(move |node| {
                 let chunker = Arc::clone(&chunker);
+                let stats = Arc::clone(&stats);
+                let error_policy = error_policy.clone();
+                let tracker = Arc::clone(&tracker);
                 let current_span = tracing::Span::current();
-                tokio::spawn(
-                    current_span.in_scope(|| async move { chunker.transform_node(node).await }),
-                )
+                cancellation::spawn(current_span.in_scope(|| async move {
+                    let path = node.path.clone();
+                    let parent_id = node.calculate_hash();
+                    let input = error_policy.keeps_nodes().then(|| node.clone());
+                    let chunks = stats
+                        .timed(chunker.transform_node(node).flatten_stream().collect())
+                        .await;
+                    let stream = stream::iter(with_lineage(parent_id, chunks))
+                        .inspect({
+                            let stats = Arc::clone(&stats);
+                            move |result| stats.record(result)
+                        })
+                        .boxed();
+                    let stream = tracker.track_stream(vec![path], stream);
+                    error_policy.handle_stream(stats.name(), input.into_iter().collect(), stream)
+                }))
                 .map_err(anyhow::Error::from)
             })
             .try_buffer_unordered(concurrency)
@@ -200,20 +524,43 @@ impl IngestionPipeline {
     /// # Returns
     ///
     /// An instance of `IngestionPipeline` with the configured storage backend.
-    pub fn then_store_with(mut self, storage: impl Persist + 'static) -> Self {
+    pub fn then_store_with<S: Persist + 'static>(mut self, storage: S) -> Self {
         let storage = Arc::new(storage);
         self.storage.push(storage.clone());
+        let stats = self.add_stage::<S>(StageKind::Storage);
+        let error_policy = self.error_policy.clone();
+        let tracker = Arc::clone(&self.tracker);
         // add storage to the stream instead of doing it at the end
-        if storage.batch_size().is_some() {
+        if let Some(batch_size) = storage.batch_size() {
             self.stream = self
                 .stream
-                .try_chunks(storage.batch_size().unwrap())
+                .try_chunks(batch_size)
                 .map_ok(move |nodes| {
                     let storage = Arc::clone(&storage);
+                    let stats = Arc::clone(&stats);
+                    let error_policy = error_policy.clone();
+                    let tracker = Arc::clone(&tracker);
                     let current_span = tracing::Span::current();
-                    tokio::spawn(
-                        current_span.in_scope(|| async move { storage.batch_store(nodes).await }),
-                    )
+                    cancellation::spawn(current_span.in_scope(|| async move {
+                        let paths = nodes.iter().map(|node| node.path.clone()).collect();
+                        let input = if error_policy.keeps_nodes() {
+                            nodes.clone()
+                        } else {
+                            Vec::new()
+                        };
+                        let results = stats
+                            .timed(async {
+                                storage.batch_store(nodes).await.collect::<Vec<_>>().await
+                            })
+                            .await;
+                        results.iter().for_each(|result| stats.record(result));
+                        let stored = results.iter().filter(|result| result.is_ok()).count();
+                        if stored > 0 {
+                            stats.record_batch_stored(stored);
+                        }
+                        let stream = tracker.track_stream(paths, stream::iter(results).boxed());
+                        error_policy.handle_stream(stats.name(), input, stream)
+                    }))
                     .map_err(anyhow::Error::from)
                 })
                 .err_into::<anyhow::Error>()
@@ -225,32 +572,71 @@ impl IngestionPipeline {
                 .stream
                 .map_ok(move |node| {
                     let storage = Arc::clone(&storage);
+                    let stats = Arc::clone(&stats);
+                    let error_policy = error_policy.clone();
+                    let tracker = Arc::clone(&tracker);
                     let current_span = tracing::Span::current();
-                    tokio::spawn(current_span.in_scope(|| async move { storage.store(node).await }))
-                        .map_err(anyhow::Error::from)
+                    cancellation::spawn(current_span.in_scope(|| async move {
+                        let path = node.path.clone();
+                        let input = error_policy.keeps_nodes().then(|| node.clone());
+                        let result = stats.timed(storage.store(node)).await;
+                        stats.record(&result);
+                        match result {
+                            Ok(node) => Ok(Some(node)),
+                            Err(error) => {
+                                tracker.fail(&path);
+                                tracker.done(&path);
+                                error_policy
+                                    .handle(stats.name(), input.into_iter().collect(), error)
+                                    .map(|()| None)
+                            }
+                        }
+                    }))
+                    .map_err(anyhow::Error::from)
                 })
                 .err_into::<anyhow::Error>()
                 .try_buffer_unordered(self.concurrency)
                 .map(|x| x.and_then(|x| x))
+                .try_filter_map(|node| async move { Ok(node) })
                 .boxed();
         }
 
         self
     }
 
+    /// Registers statistics for a new stage, named after its type.
+    ///
+    /// The error policy of the loader is applied when the first stage is added, so that a policy set
+    /// directly after `from_loader` also covers the loader.
+    fn add_stage<T>(&mut self, kind: StageKind) -> Arc<StageStats> {
+        if !self.loader_error_policy_applied {
+            let stream = std::mem::replace(&mut self.stream, stream::empty().boxed());
+            self.stream =
+                self.error_policy
+                    .handle_stream(self.stages[0].name(), Vec::new(), stream);
+            self.loader_error_policy_applied = true;
+        }
+
+        let stats = StageStats::new(kind, stage_name::<T>(), Arc::clone(&self.events));
+        self.stages.push(Arc::clone(&stats));
+        stats
+    }
+
     /// Runs the ingestion pipeline.
     ///
     /// This method processes the stream of nodes, applying all configured transformations and storing the results.
     ///
     /// # Returns
     ///
-    /// A `Result` indicating the success or failure of the pipeline execution.
+    /// A `RunReport` with statistics for every stage of the pipeline. If the pipeline was cancelled,
+    /// the report covers the nodes processed until then.
     ///
     /// # Errors
     ///
     /// Returns an error if no storage backend is configured or if any stage of the pipeline fails.
     #[tracing::instrument(skip_all, fields(total_nodes), name = "ingestion_pipeline.run")]
-    pub async fn run(mut self) -> Result<()> {
+    pub async fn run(mut self) -> Result<RunReport> {
+        let start = Instant::now();
         tracing::info!(
             "Starting ingestion pipeline with {} concurrency",
             self.concurrency
@@ -259,32 +645,100 @@ impl IngestionPipeline {
             anyhow::bail!("No storage configured for ingestion pipeline");
         }
 
-        // Ensure all storage backends are set up before processing nodes
-        let setup_futures = self
-            .storage
-            .into_iter()
-            .map(|storage| tokio::spawn(async move { storage.setup().await }))
-            .collect::<Vec<_>>();
-        futures_util::future::try_join_all(setup_futures).await?;
-
+        let storage = std::mem::take(&mut self.storage);
         let mut total_nodes = 0;
-        while self.stream.try_next().await?.is_some() {
-            total_nodes += 1;
+        let mut cancelled = false;
+        let result: Result<()> = async {
+            // Ensure all storage backends are set up before processing nodes
+            let setup_futures = storage
+                .into_iter()
+                .map(|storage| cancellation::spawn(async move { storage.setup().await }))
+                .collect::<Vec<_>>();
+            futures_util::future::try_join_all(setup_futures).await?;
+
+            loop {
+                tokio::select! {
+                    node = self.stream.try_next() => {
+                        let Some(node) = node? else {
+                            break;
+                        };
+                        total_nodes += 1;
+                        self.tracker.done(&node.path);
+                        self.tracker.run_completed().await?;
+                    }
+                    () = self.cancellation_token.cancelled(), if !cancelled => {
+                        tracing::warn!(policy = ?self.cancellation_policy, "Ingestion pipeline cancelled");
+                        cancelled = true;
+                        self.stop_loader.cancel();
+                        if self.cancellation_policy == CancellationPolicy::Abandon {
+                            break;
+                        }
+                    }
+                }
+            }
+
+            // Sources can also complete by having all their nodes dropped, i.e. a file without chunks
+            self.tracker.run_completed().await
         }
+        .await;
 
-        tracing::warn!("Processed {} nodes", total_nodes);
+        tracing::info!("Processed {} nodes", total_nodes);
         tracing::Span::current().record("total_nodes", total_nodes);
 
-        Ok(())
+        let report = RunReport {
+            stages: self.stages.iter().map(|stats| stats.report()).collect(),
+            total_nodes,
+            elapsed: start.elapsed(),
+            cancelled,
+        };
+
+        if let Err(error) = result {
+            tracing::debug!(?report, "Ingestion pipeline failed");
+            self.events.emit(|| PipelineEvent::Failed {
+                report: report.clone(),
+                message: format!("{error:#}"),
+            });
+            return Err(error);
+        }
+
+        tracing::debug!(?report, "Finished ingestion pipeline");
+        self.events.emit(|| PipelineEvent::Finished(report.clone()));
+
+        Ok(report)
     }
 }
 
+/// Sets the lineage of chunks created from the same parent. Errors are not counted as siblings.
+fn with_lineage(parent_id: u64, chunks: Vec<Result<IngestionNode>>) -> Vec<Result<IngestionNode>> {
+    let sibling_count = chunks.iter().filter(|chunk| chunk.is_ok()).count();
+
+    let mut chunk_index = 0;
+    chunks
+        .into_iter()
+        .map(|chunk| {
+            chunk.map(|chunk| {
+                let lineage = Lineage {
+                    parent_id,
+                    chunk_index,
+                    sibling_count,
+                };
+                chunk_index += 1;
+                IngestionNode {
+                    lineage: Some(lineage),
+                    ..chunk
+                }
+            })
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
-    use crate::ingestion::IngestionNode;
+    use crate::ingestion::{DeadLetterQueue, EmbedFormat, IngestionNode};
     use crate::traits::*;
+    use crate::transformers::GenericEmbed;
     use futures_util::stream;
     use mockall::Sequence;
 
@@ -348,6 +802,605 @@ mod tests {
             .then_chunk(chunker)
             .then_store_with(storage);
 
-        pipeline.run().await.unwrap();
+        let report = pipeline.run().await.unwrap();
+
+        assert_eq!(report.total_nodes, 3);
+        assert_eq!(report.nodes_loaded(), 1);
+        assert_eq!(report.errors(), 0);
+        assert_eq!(
+            report
+                .stages
+                .iter()
+                .map(|stage| (stage.kind, stage.nodes))
+                .collect::<Vec<_>>(),
+            vec![
+                (StageKind::Loader, 1),
+                (StageKind::Transformer, 1),
+                (StageKind::BatchTransformer, 1),
+                (StageKind::Chunker, 3),
+                (StageKind::Storage, 3),
+            ]
+        );
+        assert_eq!(report.stages[0].name, "MockLoader");
+    }
+
+    /// Tests that nodes skipped by the cache are reported.
+    #[test_log::test(tokio::test)]
+    async fn test_reports_cached_nodes() {
+        let mut loader = MockLoader::new();
+        let mut cache = MockNodeCache::new();
+        let mut storage = MockPersist::new();
+
+        loader.expect_into_stream().returning(|| {
+            Box::pin(stream::iter(vec![
+                Ok(IngestionNode {
+                    chunk: "cached".to_string(),
+                    ..Default::default()
+                }),
+                Ok(IngestionNode::default()),
+            ]))
+        });
+
+        cache.expect_get().returning(|node| node.chunk == "cached");
+        cache.expect_set().times(1).returning(|_| ());
+
+        storage.expect_setup().returning(|| Ok(()));
+        storage.expect_batch_size().returning(|| None);
+        storage.expect_store().times(1).returning(Ok);
+
+        let report = IngestionPipeline::from_loader(loader)
+            .filter_cached(cache)
+            .then_store_with(storage)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(report.nodes_loaded(), 2);
+        assert_eq!(report.nodes_skipped_by_cache(), 1);
+        assert_eq!(report.total_nodes, 1);
+    }
+
+    /// Tests that failing nodes are moved to the dead letter queue and the run continues.
+    #[test_log::test(tokio::test)]
+    async fn test_dead_letter_policy() {
+        let mut loader = MockLoader::new();
+        let mut transformer = MockTransformer::new();
+        let mut storage = MockPersist::new();
+
+        loader.expect_into_stream().returning(|| {
+            Box::pin(stream::iter(vec![
+                Ok(IngestionNode {
+                    chunk: "fails".to_string(),
+                    ..Default::default()
+                }),
+                Err(anyhow::anyhow!("Could not load")),
+                Ok(IngestionNode::default()),
+            ]))
+        });
+
+        transformer.expect_transform_node().returning(|node| {
+            if node.chunk == "fails" {
+                anyhow::bail!("Transformer failed")
+            }
+            Ok(node)
+        });
+        transformer.expect_concurrency().returning(|| None);
+
+        storage.expect_setup().returning(|| Ok(()));
+        storage.expect_batch_size().returning(|| None);
+        storage.expect_store().times(1).returning(Ok);
+
+        let dead_letters = DeadLetterQueue::new();
+        let report = IngestionPipeline::from_loader(loader)
+            .with_error_policy(ErrorPolicy::DeadLetter(dead_letters.clone()))
+            .then(transformer)
+            .then_store_with(storage)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(report.total_nodes, 1);
+        assert_eq!(report.errors(), 2);
+
+        let mut letters = dead_letters.take();
+        letters.sort_by(|a, b| a.stage.cmp(&b.stage));
+        assert_eq!(letters.len(), 2);
+        assert_eq!(letters[0].stage, "MockLoader");
+        assert!(letters[0].nodes.is_empty());
+        assert_eq!(letters[1].stage, "MockTransformer");
+        assert_eq!(letters[1].nodes[0].chunk, "fails");
+    }
+
+    /// Tests that a cancelled pipeline stops the loader and flushes pending storage batches.
+    #[test_log::test(tokio::test)]
+    async fn test_cancel_drains_in_flight_nodes() {
+        let mut loader = MockLoader::new();
+        let mut transformer = MockTransformer::new();
+        let mut storage = MockPersist::new();
+        let cancellation_token = CancellationToken::new();
+
+        loader.expect_into_stream().returning(|| {
+            stream::repeat_with(|| Ok(IngestionNode::default()))
+                .then(|node| async move {
+                    tokio::task::yield_now().await;
+                    node
+                })
+                .boxed()
+        });
+
+        let token = cancellation_token.clone();
+        transformer.expect_transform_node().returning(move |node| {
+            token.cancel();
+            Ok(node)
+        });
+        transformer.expect_concurrency().returning(|| None);
+
+        storage.expect_setup().returning(|| Ok(()));
+        storage.expect_batch_size().returning(|| Some(1000));
+        storage
+            .expect_batch_store()
+            .times(1)
+            .returning(|nodes| Box::pin(stream::iter(nodes.into_iter().map(Ok))));
+
+        let report = IngestionPipeline::from_loader(loader)
+            .with_cancellation_token(cancellation_token)
+            .then(transformer)
+            .then_store_with(storage)
+            .run()
+            .await
+            .unwrap();
+
+        assert!(report.cancelled);
+        assert!(report.total_nodes > 0);
+        assert_eq!(report.nodes_loaded(), report.total_nodes);
+    }
+
+    #[derive(Debug)]
+    struct CancelAndHang(CancellationToken);
+
+    #[async_trait::async_trait]
+    impl Transformer for CancelAndHang {
+        async fn transform_node(&self, _node: IngestionNode) -> Result<IngestionNode> {
+            self.0.cancel();
+            futures_util::future::pending().await
+        }
+    }
+
+    /// Tests that in-flight nodes are dropped when cancelling with the abandon policy.
+    #[test_log::test(tokio::test)]
+    async fn test_cancel_abandons_in_flight_nodes() {
+        let mut loader = MockLoader::new();
+        let mut storage = MockPersist::new();
+        let cancellation_token = CancellationToken::new();
+
+        loader
+            .expect_into_stream()
+            .returning(|| stream::repeat_with(|| Ok(IngestionNode::default())).boxed());
+
+        storage.expect_setup().returning(|| Ok(()));
+        storage.expect_batch_size().returning(|| None);
+        storage.expect_store().never();
+
+        let report = IngestionPipeline::from_loader(loader)
+            .with_cancellation_token(cancellation_token.clone())
+            .with_cancellation_policy(CancellationPolicy::Abandon)
+            .then(CancelAndHang(cancellation_token))
+            .then_store_with(storage)
+            .run()
+            .await
+            .unwrap();
+
+        assert!(report.cancelled);
+        assert_eq!(report.total_nodes, 0);
+    }
+
+    /// Tests that nodes are checkpointed only after all their chunks are stored.
+    #[test_log::test(tokio::test)]
+    async fn test_checkpoint_after_all_chunks_are_stored() {
+        let mut loader = MockLoader::new();
+        let mut checkpoint = MockCheckpoint::new();
+        let mut chunker = MockChunkerTransformer::new();
+        let mut storage = MockPersist::new();
+
+        loader.expect_into_stream().returning(|| {
+            Box::pin(stream::iter(["completed", "stored", "fails"].map(|path| {
+                Ok(IngestionNode {
+                    path: path.into(),
+                    ..Default::default()
+                })
+            })))
+        });
+
+        checkpoint
+            .expect_is_completed()
+            .returning(|node| Ok(node.path.to_str() == Some("completed")));
+        checkpoint
+            .expect_mark_completed()
+            .times(1)
+            .withf(|node| node.path.to_str() == Some("stored"))
+            .returning(|_| Ok(()));
+
+        chunker.expect_transform_node().returning(|node| {
+            Box::pin(stream::iter((0..2).map(move |i| {
+                Ok(IngestionNode {
+                    chunk: format!("chunk_{i}"),
+                    ..node.clone()
+                })
+            })))
+        });
+        chunker.expect_concurrency().returning(|| None);
+
+        storage.expect_setup().returning(|| Ok(()));
+        storage.expect_batch_size().returning(|| None);
+        storage.expect_store().times(4).returning(|node| {
+            if node.path.to_str() == Some("fails") && node.chunk == "chunk_1" {
+                anyhow::bail!("Storage failed")
+            }
+            Ok(node)
+        });
+
+        let report = IngestionPipeline::from_loader(loader)
+            .with_error_policy(ErrorPolicy::SkipAndLog)
+            .with_checkpoint(checkpoint)
+            .then_chunk(chunker)
+            .then_store_with(storage)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(report.nodes_skipped_by_checkpoint(), 1);
+        assert_eq!(report.total_nodes, 3);
+    }
+
+    /// Tests that an oversized node in an embed batch fails only its own source.
+    #[test_log::test(tokio::test)]
+    async fn test_oversized_node_fails_only_its_source() {
+        let mut loader = MockLoader::new();
+        let mut checkpoint = MockCheckpoint::new();
+        let mut model = MockEmbed::new();
+        let mut storage = MockPersist::new();
+
+        loader.expect_into_stream().returning(|| {
+            Box::pin(stream::iter(
+                [("small.rs", "short"), ("large.rs", "much too long")].map(|(path, chunk)| {
+                    Ok(IngestionNode {
+                        path: path.into(),
+                        chunk: chunk.into(),
+                        ..Default::default()
+                    })
+                }),
+            ))
+        });
+
+        checkpoint.expect_is_completed().returning(|_| Ok(false));
+        checkpoint
+            .expect_mark_completed()
+            .times(1)
+            .withf(|node| node.path.to_str() == Some("small.rs"))
+            .returning(|_| Ok(()));
+
+        model.expect_tokenizer().returning(|| None);
+        model
+            .expect_embed()
+            .times(1)
+            .withf(|input| input == &["short".to_string()])
+            .returning(|input| Ok(input.iter().map(|_| vec![1.0]).collect()));
+
+        storage.expect_setup().returning(|| Ok(()));
+        storage.expect_batch_size().returning(|| None);
+        storage.expect_store().times(1).returning(Ok);
+
+        let dead_letters = DeadLetterQueue::new();
+        let report = IngestionPipeline::from_loader(loader)
+            .with_error_policy(ErrorPolicy::DeadLetter(dead_letters.clone()))
+            .with_checkpoint(checkpoint)
+            .then_in_batch(
+                2,
+                GenericEmbed::new(model)
+                    .with_embed_format(EmbedFormat::chunk_only())
+                    .with_max_tokens(2),
+            )
+            .then_store_with(storage)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(report.total_nodes, 1);
+        let letters = dead_letters.take();
+        assert_eq!(letters.len(), 1);
+        assert_eq!(letters[0].nodes.len(), 1);
+        assert_eq!(letters[0].nodes[0].path.to_str(), Some("large.rs"));
+    }
+
+    /// Tests that chunks are linked to their parent and siblings.
+    #[test_log::test(tokio::test)]
+    async fn test_chunks_have_lineage() {
+        let mut loader = MockLoader::new();
+        let mut chunker = MockChunkerTransformer::new();
+        let mut storage = MockPersist::new();
+        let parent = IngestionNode {
+            path: "document".into(),
+            chunk: "one two three".to_string(),
+            ..Default::default()
+        };
+        let parent_id = parent.calculate_hash();
+
+        loader
+            .expect_into_stream()
+            .returning(move || Box::pin(stream::iter(vec![Ok(parent.clone())])));
+        chunker.expect_transform_node().returning(|node| {
+            let chunks = node
+                .chunk
+                .split(' ')
+                .map(|chunk| {
+                    Ok(IngestionNode {
+                        chunk: chunk.to_string(),
+                        ..node.clone()
+                    })
+                })
+                .chain([Err(anyhow::anyhow!("Chunking failed"))])
+                .collect::<Vec<_>>();
+            Box::pin(stream::iter(chunks))
+        });
+        chunker.expect_concurrency().returning(|| None);
+
+        let stored = Arc::new(std::sync::Mutex::new(Vec::new()));
+        storage.expect_setup().returning(|| Ok(()));
+        storage.expect_batch_size().returning(|| None);
+        storage.expect_store().returning({
+            let stored = Arc::clone(&stored);
+            move |node| {
+                stored.lock().unwrap().push(node.clone());
+                Ok(node)
+            }
+        });
+
+        IngestionPipeline::from_loader(loader)
+            .with_error_policy(ErrorPolicy::SkipAndLog)
+            .then_chunk(chunker)
+            .then_store_with(storage)
+            .run()
+            .await
+            .unwrap();
+
+        let mut stored = stored.lock().unwrap().clone();
+        stored.sort_by_key(|node| node.lineage.map(|lineage| lineage.chunk_index));
+        assert_eq!(
+            stored
+                .iter()
+                .map(|node| (node.chunk.as_str(), node.lineage))
+                .collect::<Vec<_>>(),
+            ["one", "two", "three"]
+                .into_iter()
+                .enumerate()
+                .map(|(chunk_index, chunk)| (
+                    chunk,
+                    Some(Lineage {
+                        parent_id,
+                        chunk_index,
+                        sibling_count: 3
+                    })
+                ))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    /// Tests that nodes are cached only after they are stored with `CacheMode::AfterStore`.
+    #[test_log::test(tokio::test)]
+    async fn test_cache_after_store() {
+        let mut loader = MockLoader::new();
+        let mut cache = MockNodeCache::new();
+        let mut transformer = MockTransformer::new();
+        let mut storage = MockPersist::new();
+
+        loader.expect_into_stream().returning(|| {
+            Box::pin(stream::iter(["stored", "fails"].map(|path| {
+                Ok(IngestionNode {
+                    path: path.into(),
+                    ..Default::default()
+                })
+            })))
+        });
+
+        cache.expect_get().returning(|_| false);
+        cache
+            .expect_set()
+            .times(1)
+            .withf(|node| node.path.to_str() == Some("stored"))
+            .returning(|_| ());
+
+        transformer.expect_transform_node().returning(|node| {
+            if node.path.to_str() == Some("fails") {
+                anyhow::bail!("Transformer failed")
+            }
+            Ok(node)
+        });
+        transformer.expect_concurrency().returning(|| None);
+
+        storage.expect_setup().returning(|| Ok(()));
+        storage.expect_batch_size().returning(|| None);
+        storage.expect_store().times(1).returning(Ok);
+
+        let report = IngestionPipeline::from_loader(loader)
+            .with_error_policy(ErrorPolicy::SkipAndLog)
+            .with_cache_mode(CacheMode::AfterStore)
+            .filter_cached(cache)
+            .then(transformer)
+            .then_store_with(storage)
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(report.total_nodes, 1);
+    }
+
+    /// Tests that events are emitted for every stage.
+    #[test_log::test(tokio::test)]
+    async fn test_emits_events() {
+        let mut loader = MockLoader::new();
+        let mut transformer = MockTransformer::new();
+        let mut storage = MockPersist::new();
+
+        loader.expect_into_stream().returning(|| {
+            Box::pin(stream::iter(vec![
+                Ok(IngestionNode::default()),
+                Ok(IngestionNode {
+                    chunk: "fails".to_string(),
+                    ..Default::default()
+                }),
+            ]))
+        });
+
+        transformer.expect_transform_node().returning(|node| {
+            if node.chunk == "fails" {
+                anyhow::bail!("Transformer failed")
+            }
+            Ok(node)
+        });
+        transformer.expect_concurrency().returning(|| None);
+
+        storage.expect_setup().returning(|| Ok(()));
+        storage.expect_batch_size().returning(|| None);
+        storage.expect_store().returning(Ok);
+
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        IngestionPipeline::from_loader(loader)
+            .with_error_policy(ErrorPolicy::SkipAndLog)
+            .then(transformer)
+            .then_store_with(storage)
+            .on_event({
+                let events = Arc::clone(&events);
+                move |event| events.lock().unwrap().push(event.clone())
+            })
+            .run()
+            .await
+            .unwrap();
+
+        let events = events.lock().unwrap();
+        let count = |f: fn(&PipelineEvent) -> bool| events.iter().filter(|e| f(e)).count();
+        assert_eq!(count(|e| matches!(e, PipelineEvent::NodeLoaded { .. })), 2);
+        assert_eq!(
+            count(|e| matches!(
+                e,
+                PipelineEvent::NodeProcessed {
+                    kind: StageKind::Transformer,
+                    ..
+                }
+            )),
+            1
+        );
+        assert_eq!(
+            count(
+                |e| matches!(e, PipelineEvent::Error { message, .. } if message == "Transformer failed")
+            ),
+            1
+        );
+        assert_eq!(count(|e| matches!(e, PipelineEvent::NodeStored { .. })), 1);
+        assert!(
+            matches!(events.last(), Some(PipelineEvent::Finished(report)) if report.total_nodes == 1)
+        );
+    }
+
+    /// Tests that errors abort the run by default.
+    #[test_log::test(tokio::test)]
+    async fn test_fails_fast_by_default() {
+        let mut loader = MockLoader::new();
+        let mut storage = MockPersist::new();
+
+        loader
+            .expect_into_stream()
+            .returning(|| Box::pin(stream::iter(vec![Err(anyhow::anyhow!("Could not load"))])));
+
+        storage.expect_setup().returning(|| Ok(()));
+        storage.expect_batch_size().returning(|| None);
+        storage.expect_store().never();
+
+        let result = IngestionPipeline::from_loader(loader)
+            .then_store_with(storage)
+            .run()
+            .await;
+
+        assert!(result.is_err());
+    }
+
+    /// Tests that batch storage emits an event per stored batch.
+    #[test_log::test(tokio::test)]
+    async fn test_emits_batch_stored_events() {
+        let mut loader = MockLoader::new();
+        let mut storage = MockPersist::new();
+
+        loader.expect_into_stream().returning(|| {
+            stream::iter((0..3).map(|i| {
+                Ok(IngestionNode {
+                    path: format!("file_{i}.rs").into(),
+                    ..Default::default()
+                })
+            }))
+            .boxed()
+        });
+
+        storage.expect_setup().returning(|| Ok(()));
+        storage.expect_batch_size().returning(|| Some(2));
+        storage
+            .expect_batch_store()
+            .returning(|nodes| Box::pin(stream::iter(nodes.into_iter().map(Ok))));
+
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        IngestionPipeline::from_loader(loader)
+            .then_store_with(storage)
+            .on_event({
+                let events = Arc::clone(&events);
+                move |event| events.lock().unwrap().push(event.clone())
+            })
+            .run()
+            .await
+            .unwrap();
+
+        let events = events.lock().unwrap();
+        let mut batches = events
+            .iter()
+            .filter_map(|event| match event {
+                PipelineEvent::BatchStored { count, .. } => Some(*count),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        batches.sort_unstable();
+        assert_eq!(batches, vec![1, 2]);
+        assert!(matches!(events.last(), Some(PipelineEvent::Finished(_))));
+    }
+
+    /// Tests that a run stopped by an error emits a failed event instead of a finished event.
+    #[test_log::test(tokio::test)]
+    async fn test_emits_failed_event_on_error() {
+        let mut loader = MockLoader::new();
+        let mut storage = MockPersist::new();
+
+        loader
+            .expect_into_stream()
+            .returning(|| Box::pin(stream::iter(vec![Err(anyhow::anyhow!("Could not load"))])));
+
+        storage.expect_setup().returning(|| Ok(()));
+        storage.expect_batch_size().returning(|| None);
+
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let result = IngestionPipeline::from_loader(loader)
+            .then_store_with(storage)
+            .on_event({
+                let events = Arc::clone(&events);
+                move |event| events.lock().unwrap().push(event.clone())
+            })
+            .run()
+            .await;
+
+        assert!(result.is_err());
+        let events = events.lock().unwrap();
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, PipelineEvent::Finished(_))));
+        assert!(matches!(
+            events.last(),
+            Some(PipelineEvent::Failed { report, message })
+                if message == "Could not load" && report.errors() == 1
+        ));
     }
 }
@@ -40,5 +40,4 @@ use super::IngestionNode;
 /// - The stream may yield errors (`Err` variants) instead of valid `IngestionNode` items. These errors should be handled appropriately
 ///   to ensure the robustness of the ingestion pipeline.
 /// - The stream must be pinned to ensure that its memory location remains fixed, which is necessary for certain asynchronous operations.
-
 pub type IngestionStream = Pin<Box<dyn Stream<Item = Result<IngestionNode>> + Send>>;
@@ -11,6 +11,20 @@
 //!   transformation and storage to be configured and executed asynchronously.
 //! - `IngestionStream`: A type alias for a pinned, boxed, dynamically-dispatched stream of `IngestionNode` items,
 //!   facilitating efficient and scalable ingestion workflows.
+//! - `EmbedFormat`: Determines the text that is embedded for a node, i.e. which metadata is included and in
+//!   what order.
+//! - `ErrorPolicy`: Determines whether errors abort the pipeline or skip the failing nodes, optionally
+//!   recording them in a `DeadLetterQueue`.
+//! - `NodeError`: Ties an error in the output of a batch stage to the node that caused it, so only that
+//!   node's source is failed.
+//! - `Retry`: Wraps a transformer, chunker or storage backend to retry transient errors with exponential backoff.
+//! - `CancellationPolicy`: Determines whether in-flight nodes are drained or abandoned when a pipeline is
+//!   cancelled with a `CancellationToken`.
+//! - `CacheMode`: Determines whether cached nodes are marked immediately or only after they are stored.
+//! - `with_checkpoint`: Records nodes once all their chunks are stored, so an interrupted run can resume
+//!   where it stopped.
+//! - `PipelineEvent`: Events emitted while a pipeline runs, i.e. to drive progress bars.
+//! - `RunReport`: Statistics of a pipeline run, such as nodes loaded, skipped, stored and errors per stage.
 //!
 //! # Usage
 //!
@@ -18,10 +32,29 @@
 //! ingestion pipelines. These pipelines can be customized with different loaders, transformers, and storage
 //! backends to meet specific requirements.
 
+mod cache_mode;
+mod cancellation;
+mod embed_format;
+mod error_policy;
+mod events;
 mod ingestion_node;
 mod ingestion_pipeline;
 mod ingestion_stream;
+mod node_error;
+mod node_tracker;
+mod retry;
+mod run_report;
 
+pub use cache_mode::CacheMode;
+pub use cancellation::CancellationPolicy;
+pub use embed_format::EmbedFormat;
+pub use error_policy::{DeadLetter, DeadLetterQueue, ErrorPolicy};
+pub use events::PipelineEvent;
+pub(crate) use ingestion_node::StableHasher;
 pub use ingestion_node::*;
 pub use ingestion_pipeline::*;
 pub use ingestion_stream::*;
+pub use node_error::NodeError;
+pub use retry::{Retry, RetryPolicy};
+pub use run_report::{RunReport, StageKind, StageReport};
+pub use tokio_util::sync::CancellationToken;
@@ -0,0 +1,67 @@
+//! This module provides `NodeError`, which ties an error of a batch stage to a single node.
+//!
+//! When a batch transformer or storage backend returns an error in its output stream, the
+//! pipeline cannot tell which of the nodes in the batch caused it, so the sources of all of them
+//! are failed. Wrapping the error in a `NodeError` fails only the source of that node, and with
+//! `ErrorPolicy::DeadLetter` records only the nodes of that source.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+/// An error caused by the node with the given path.
+#[derive(Debug)]
+pub struct NodeError {
+    path: PathBuf,
+    source: anyhow::Error,
+}
+
+impl NodeError {
+    pub fn new(path: impl Into<PathBuf>, source: impl Into<anyhow::Error>) -> Self {
+        Self {
+            path: path.into(),
+            source: source.into(),
+        }
+    }
+
+    /// The path of the node that caused the error.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the path of the node that caused the error, if it is a `NodeError`.
+    pub(crate) fn path_of(error: &anyhow::Error) -> Option<&Path> {
+        error.downcast_ref::<Self>().map(Self::path)
+    }
+}
+
+impl fmt::Display for NodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed to process {}", self.path.display())
+    }
+}
+
+impl std::error::Error for NodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_survives_context() {
+        let error = anyhow::Error::from(NodeError::new("src/main.rs", anyhow::anyhow!("failed")))
+            .context("Stage failed");
+
+        assert_eq!(NodeError::path_of(&error), Some(Path::new("src/main.rs")));
+        assert_eq!(
+            format!("{error:#}"),
+            "Stage failed: Failed to process src/main.rs: failed"
+        );
+        assert_eq!(NodeError::path_of(&anyhow::anyhow!("failed")), None);
+    }
+}
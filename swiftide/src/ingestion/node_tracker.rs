@@ -0,0 +1,241 @@
+//! This module tracks nodes through the pipeline, so work can be done once every node derived
+//! from a loaded node has made it through all stages.
+//!
+//! Nodes are tracked by their path, as set by the loader. Transformers keep the path and chunkers
+//! copy it to every chunk, so all nodes with the same path are considered part of the same source.
+//! A source is completed when none of its nodes are left in the pipeline and none of them failed.
+//!
+//! To avoid completing a source too early, chunkers and batch stages first track the nodes they
+//! emit and only release their input when their output stream has ended.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use anyhow::Result;
+use futures_util::{future, future::BoxFuture, stream, StreamExt};
+
+use super::{IngestionNode, IngestionStream, NodeError};
+
+/// Work to do once a source is completed.
+pub(crate) type CompletionAction = Box<dyn FnOnce() -> BoxFuture<'static, Result<()>> + Send>;
+
+#[derive(Default)]
+struct Source {
+    pending: usize,
+    failed: bool,
+    on_completed: Vec<CompletionAction>,
+}
+
+/// Counts the nodes of every source that are still in the pipeline.
+#[derive(Default)]
+pub(crate) struct NodeTracker {
+    sources: Mutex<HashMap<PathBuf, Source>>,
+    completed: Mutex<Vec<CompletionAction>>,
+}
+
+impl NodeTracker {
+    /// Records a node of the source entering the pipeline.
+    pub(crate) fn track(&self, path: &Path) {
+        lock(&self.sources)
+            .entry(path.to_path_buf())
+            .or_default()
+            .pending += 1;
+    }
+
+    /// Marks the source as failed, so it will never be completed.
+    pub(crate) fn fail(&self, path: &Path) {
+        if let Some(source) = lock(&self.sources).get_mut(path) {
+            source.failed = true;
+        }
+    }
+
+    /// Registers work to do once the source is completed.
+    pub(crate) fn on_completed(&self, path: &Path, action: CompletionAction) {
+        if let Some(source) = lock(&self.sources).get_mut(path) {
+            source.on_completed.push(action);
+        }
+    }
+
+    /// Records a node of the source leaving the pipeline, either because it made it through all
+    /// stages or because a stage dropped it.
+    pub(crate) fn done(&self, path: &Path) {
+        let mut sources = lock(&self.sources);
+        let Some(source) = sources.get_mut(path) else {
+            return;
+        };
+
+        source.pending = source.pending.saturating_sub(1);
+        if source.pending > 0 {
+            return;
+        }
+
+        if let Some(source) = sources.remove(path) {
+            if !source.failed {
+                lock(&self.completed).extend(source.on_completed);
+            }
+        }
+    }
+
+    /// Tracks the output of a stage that consumed the nodes of the given sources.
+    ///
+    /// Every emitted node is tracked before it is passed on, and the inputs are released when the
+    /// stream ends. A `NodeError` fails the source of its node, any other error fails all input
+    /// sources.
+    pub(crate) fn track_stream(
+        self: &Arc<Self>,
+        inputs: Vec<PathBuf>,
+        stream: IngestionStream,
+    ) -> IngestionStream {
+        let on_item = {
+            let tracker = Arc::clone(self);
+            let inputs = inputs.clone();
+            move |result: &Result<IngestionNode>| match result {
+                Ok(node) => tracker.track(&node.path),
+                Err(error) => match NodeError::path_of(error) {
+                    Some(path) => tracker.fail(path),
+                    None => inputs.iter().for_each(|path| tracker.fail(path)),
+                },
+            }
+        };
+        let tracker = Arc::clone(self);
+        let on_end = stream::once(async move {
+            inputs.iter().for_each(|path| tracker.done(path));
+            None
+        });
+
+        stream
+            .inspect(on_item)
+            .map(Some)
+            .chain(on_end)
+            .filter_map(future::ready)
+            .boxed()
+    }
+
+    /// Runs the work registered for all sources completed so far.
+    pub(crate) async fn run_completed(&self) -> Result<()> {
+        let actions = std::mem::take(&mut *lock(&self.completed));
+        for action in actions {
+            action().await?;
+        }
+        Ok(())
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    // Counters are updated in a single step, so a panic cannot leave them inconsistent
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::FutureExt as _;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn counting_action(counter: &Arc<AtomicUsize>) -> CompletionAction {
+        let counter = Arc::clone(counter);
+        Box::new(move || {
+            async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+            .boxed()
+        })
+    }
+
+    #[tokio::test]
+    async fn test_completes_when_all_chunks_are_done() {
+        let tracker = Arc::new(NodeTracker::default());
+        let completed = Arc::new(AtomicUsize::new(0));
+        let path = Path::new("file.rs");
+
+        tracker.track(path);
+        tracker.on_completed(path, counting_action(&completed));
+
+        let chunks = tracker
+            .track_stream(
+                vec![path.to_path_buf()],
+                stream::iter(vec![
+                    Ok(IngestionNode {
+                        path: path.to_path_buf(),
+                        ..Default::default()
+                    }),
+                    Ok(IngestionNode {
+                        path: path.to_path_buf(),
+                        ..Default::default()
+                    }),
+                ])
+                .boxed(),
+            )
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(chunks.len(), 2);
+
+        // The first chunk is stored
+        tracker.done(path);
+        tracker.run_completed().await.unwrap();
+        assert_eq!(completed.load(Ordering::SeqCst), 0);
+
+        // The second chunk is stored
+        tracker.done(path);
+        tracker.run_completed().await.unwrap();
+        assert_eq!(completed.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_failed_sources_are_not_completed() {
+        let tracker = Arc::new(NodeTracker::default());
+        let completed = Arc::new(AtomicUsize::new(0));
+        let path = Path::new("file.rs");
+
+        tracker.track(path);
+        tracker.on_completed(path, counting_action(&completed));
+
+        let _ = tracker
+            .track_stream(
+                vec![path.to_path_buf()],
+                stream::iter(vec![Err(anyhow::anyhow!("failed"))]).boxed(),
+            )
+            .collect::<Vec<_>>()
+            .await;
+
+        tracker.run_completed().await.unwrap();
+        assert_eq!(completed.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_node_errors_fail_only_their_source() {
+        let tracker = Arc::new(NodeTracker::default());
+        let completed = Arc::new(AtomicUsize::new(0));
+        let ok = Path::new("ok.rs");
+        let failed = Path::new("failed.rs");
+
+        for path in [ok, failed] {
+            tracker.track(path);
+            tracker.on_completed(path, counting_action(&completed));
+        }
+
+        let _ = tracker
+            .track_stream(
+                vec![ok.to_path_buf(), failed.to_path_buf()],
+                stream::iter(vec![
+                    Ok(IngestionNode {
+                        path: ok.to_path_buf(),
+                        ..Default::default()
+                    }),
+                    Err(NodeError::new(failed, anyhow::anyhow!("failed")).into()),
+                ])
+                .boxed(),
+            )
+            .collect::<Vec<_>>()
+            .await;
+
+        tracker.done(ok);
+        tracker.run_completed().await.unwrap();
+        assert_eq!(completed.load(Ordering::SeqCst), 1);
+    }
+}
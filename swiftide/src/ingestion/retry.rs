@@ -0,0 +1,445 @@
+//! This module provides retries with exponential backoff for pipeline stages.
+//!
+//! Any `Transformer`, `BatchableTransformer`, `ChunkerTransformer` or `Persist` can be wrapped in
+//! `Retry`, which retries failed attempts according to a `RetryPolicy`. This is useful for stages
+//! that call external services, which regularly fail with transient errors like rate limits or
+//! server errors.
+//!
+//! Batch and chunker stages return streams, which are collected before deciding to retry. If any
+//! item of the stream is a retryable error, the whole batch or node is retried.
+
+use std::{fmt::Debug, future::Future, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use futures_util::{stream, StreamExt};
+use rand::Rng as _;
+use tracing::Instrument as _;
+
+use crate::{BatchableTransformer, ChunkerTransformer, Persist, Transformer};
+
+use super::{IngestionNode, IngestionStream};
+
+const DEFAULT_MAX_ATTEMPTS: usize = 3;
+const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+const DEFAULT_MULTIPLIER: f64 = 2.0;
+
+type RetryAfter = Arc<dyn Fn(&anyhow::Error) -> Option<Duration> + Send + Sync>;
+
+/// Determines how often and how fast a failed stage is retried.
+///
+/// The delay before retry `n` is `initial_backoff * multiplier^(n - 1)`, capped at `max_backoff`.
+/// With jitter enabled, a random delay between zero and the computed delay is used instead, so
+/// concurrent tasks do not retry in lockstep. If the error asks to wait longer, i.e. with a
+/// `retry-after` header, see `with_retry_after`, that delay is used instead.
+#[derive(Clone)]
+pub struct RetryPolicy {
+    max_attempts: usize,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: f64,
+    jitter: bool,
+    retryable: Arc<dyn Fn(&anyhow::Error) -> bool + Send + Sync>,
+    retry_after: RetryAfter,
+}
+
+impl Default for RetryPolicy {
+    /// Creates a `RetryPolicy` with 3 attempts, backoff from 500ms up to 30s with jitter, retrying all errors.
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            multiplier: DEFAULT_MULTIPLIER,
+            jitter: true,
+            retryable: Arc::new(|_| true),
+            retry_after: Arc::new(|_| None),
+        }
+    }
+}
+
+impl Debug for RetryPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("initial_backoff", &self.initial_backoff)
+            .field("max_backoff", &self.max_backoff)
+            .field("multiplier", &self.multiplier)
+            .field("jitter", &self.jitter)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RetryPolicy {
+    /// Sets the maximum number of attempts, including the first one.
+    pub fn with_max_attempts(mut self, max_attempts: usize) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Sets the delay before the first retry.
+    pub fn with_initial_backoff(mut self, initial_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self
+    }
+
+    /// Sets the maximum delay between retries.
+    pub fn with_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Sets the factor the delay grows with after every retry.
+    ///
+    /// A multiplier below 1.0 is raised to 1.0, and one that is not finite is replaced by the
+    /// default of 2.0.
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = if !multiplier.is_finite() {
+            tracing::warn!(
+                multiplier,
+                "Backoff multiplier is not finite, using the default"
+            );
+            DEFAULT_MULTIPLIER
+        } else if multiplier < 1.0 {
+            tracing::warn!(multiplier, "Backoff multiplier is below 1.0, using 1.0");
+            1.0
+        } else {
+            multiplier
+        };
+        self
+    }
+
+    /// Enables or disables jitter on the delay.
+    pub fn with_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Sets the predicate deciding which errors are retried. By default all errors are retried.
+    pub fn with_retryable(
+        mut self,
+        retryable: impl Fn(&anyhow::Error) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.retryable = Arc::new(retryable);
+        self
+    }
+
+    /// Sets how to read the delay an error asks to wait before retrying, i.e. from a `retry-after`
+    /// header. The larger of this delay and the backoff is used. By default errors are not asked.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// RetryPolicy::default()
+    ///     .with_retryable(anthropic::is_retryable)
+    ///     .with_retry_after(anthropic::retry_after)
+    /// ```
+    pub fn with_retry_after(
+        mut self,
+        retry_after: impl Fn(&anyhow::Error) -> Option<Duration> + Send + Sync + 'static,
+    ) -> Self {
+        self.retry_after = Arc::new(retry_after);
+        self
+    }
+
+    /// Returns the delay before the given retry after the error, starting at 1.
+    fn delay(&self, retry: usize, error: &anyhow::Error) -> Duration {
+        let backoff = self.backoff(retry);
+        (self.retry_after)(error).map_or(backoff, |retry_after| retry_after.max(backoff))
+    }
+
+    /// Returns the delay before the given retry, starting at 1.
+    fn backoff(&self, retry: usize) -> Duration {
+        let exponent = i32::try_from(retry.saturating_sub(1)).unwrap_or(i32::MAX);
+        // Computed in f64 and capped before converting, as the delay overflows `Duration` after
+        // enough retries
+        let backoff = (self.initial_backoff.as_secs_f64() * self.multiplier.powi(exponent))
+            .min(self.max_backoff.as_secs_f64());
+        let backoff = Duration::try_from_secs_f64(backoff).unwrap_or(self.max_backoff);
+
+        if self.jitter {
+            backoff.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+        } else {
+            backoff
+        }
+    }
+
+    /// Runs the operation until it succeeds, fails with an error that is not retryable, or runs
+    /// out of attempts. Returns the output of the last attempt.
+    async fn run<T, F, Fut>(
+        &self,
+        mut operation: F,
+        error_of: impl Fn(&T) -> Option<&anyhow::Error>,
+    ) -> T
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        let mut attempt = 1;
+        loop {
+            let output = operation()
+                .instrument(tracing::info_span!("retry.attempt", attempt))
+                .await;
+
+            match error_of(&output) {
+                Some(error) if attempt < self.max_attempts && (self.retryable)(error) => {
+                    let delay = self.delay(attempt, error);
+                    tracing::warn!(attempt, ?delay, error = ?error, "Attempt failed, retrying");
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                _ => return output,
+            }
+        }
+    }
+
+    async fn run_result<T, F, Fut>(&self, operation: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        self.run(operation, |result| result.as_ref().err()).await
+    }
+
+    async fn run_stream<F, Fut>(&self, mut operation: F) -> IngestionStream
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = IngestionStream>,
+    {
+        let results = self
+            .run(
+                || {
+                    let stream = operation();
+                    async move { stream.await.collect::<Vec<_>>().await }
+                },
+                |results| results.iter().find_map(|result| result.as_ref().err()),
+            )
+            .await;
+
+        stream::iter(results).boxed()
+    }
+}
+
+/// Wraps a pipeline stage and retries it according to a `RetryPolicy`.
+///
+/// # Example
+///
+/// ```ignore
+/// IngestionPipeline::from_loader(loader)
+///     .then(Retry::new(MetadataQACode::new(openai_client.clone()), RetryPolicy::default()))
+/// ```
+#[derive(Debug)]
+pub struct Retry<T> {
+    inner: T,
+    policy: RetryPolicy,
+}
+
+impl<T> Retry<T> {
+    /// Wraps a stage in a retry policy.
+    ///
+    /// # Arguments
+    ///
+    /// * `inner` - The transformer, chunker or storage to retry.
+    /// * `policy` - The `RetryPolicy` to retry with.
+    pub fn new(inner: T, policy: RetryPolicy) -> Self {
+        Self { inner, policy }
+    }
+}
+
+#[async_trait]
+impl<T: Transformer> Transformer for Retry<T> {
+    #[tracing::instrument(skip_all, name = "retry.transform_node")]
+    async fn transform_node(&self, node: IngestionNode) -> Result<IngestionNode> {
+        self.policy
+            .run_result(|| self.inner.transform_node(node.clone()))
+            .await
+    }
+
+    fn concurrency(&self) -> Option<usize> {
+        self.inner.concurrency()
+    }
+}
+
+#[async_trait]
+impl<T: BatchableTransformer> BatchableTransformer for Retry<T> {
+    fn batch_size(&self) -> Option<usize> {
+        self.inner.batch_size()
+    }
+
+    #[tracing::instrument(skip_all, name = "retry.batch_transform")]
+    async fn batch_transform(&self, nodes: Vec<IngestionNode>) -> IngestionStream {
+        self.policy
+            .run_stream(|| self.inner.batch_transform(nodes.clone()))
+            .await
+    }
+
+    fn concurrency(&self) -> Option<usize> {
+        self.inner.concurrency()
+    }
+}
+
+#[async_trait]
+impl<T: ChunkerTransformer> ChunkerTransformer for Retry<T> {
+    #[tracing::instrument(skip_all, name = "retry.chunk_node")]
+    async fn transform_node(&self, node: IngestionNode) -> IngestionStream {
+        self.policy
+            .run_stream(|| self.inner.transform_node(node.clone()))
+            .await
+    }
+
+    fn concurrency(&self) -> Option<usize> {
+        self.inner.concurrency()
+    }
+}
+
+#[async_trait]
+impl<T: Persist> Persist for Retry<T> {
+    #[tracing::instrument(skip_all, name = "retry.setup")]
+    async fn setup(&self) -> Result<()> {
+        self.policy.run_result(|| self.inner.setup()).await
+    }
+
+    #[tracing::instrument(skip_all, name = "retry.store")]
+    async fn store(&self, node: IngestionNode) -> Result<IngestionNode> {
+        self.policy
+            .run_result(|| self.inner.store(node.clone()))
+            .await
+    }
+
+    #[tracing::instrument(skip_all, name = "retry.batch_store")]
+    async fn batch_store(&self, nodes: Vec<IngestionNode>) -> IngestionStream {
+        self.policy
+            .run_stream(|| self.inner.batch_store(nodes.clone()))
+            .await
+    }
+
+    fn batch_size(&self) -> Option<usize> {
+        self.inner.batch_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::*;
+
+    fn fast_policy() -> RetryPolicy {
+        RetryPolicy::default()
+            .with_initial_backoff(Duration::from_millis(1))
+            .with_jitter(false)
+    }
+
+    #[tokio::test]
+    async fn test_retries_until_success() {
+        let mut transformer = MockTransformer::new();
+        let mut seq = mockall::Sequence::new();
+        transformer
+            .expect_transform_node()
+            .times(2)
+            .in_sequence(&mut seq)
+            .returning(|_| anyhow::bail!("Too many requests"));
+        transformer
+            .expect_transform_node()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(Ok);
+
+        let retry = Retry::new(transformer, fast_policy());
+
+        assert!(retry.transform_node(IngestionNode::default()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_attempts() {
+        let mut storage = MockPersist::new();
+        storage
+            .expect_store()
+            .times(2)
+            .returning(|_| anyhow::bail!("Service unavailable"));
+
+        let retry = Retry::new(storage, fast_policy().with_max_attempts(2));
+
+        assert!(retry.store(IngestionNode::default()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_does_not_retry_unretryable_errors() {
+        let mut chunker = MockChunkerTransformer::new();
+        chunker
+            .expect_transform_node()
+            .times(1)
+            .returning(|_| stream::iter(vec![Err(anyhow::anyhow!("Invalid syntax"))]).boxed());
+
+        let retry = Retry::new(
+            chunker,
+            fast_policy().with_retryable(|error| !error.to_string().contains("syntax")),
+        );
+
+        let results = retry
+            .transform_node(IngestionNode::default())
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(results.len(), 1);
+        assert!(results[0].is_err());
+    }
+
+    #[test]
+    fn test_backoff_is_capped() {
+        let policy = RetryPolicy::default()
+            .with_initial_backoff(Duration::from_secs(1))
+            .with_max_backoff(Duration::from_secs(5))
+            .with_jitter(false);
+
+        assert_eq!(policy.backoff(1), Duration::from_secs(1));
+        assert_eq!(policy.backoff(2), Duration::from_secs(2));
+        assert_eq!(policy.backoff(10), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_backoff_of_late_retries_does_not_overflow() {
+        let policy = RetryPolicy::default()
+            .with_max_attempts(usize::MAX)
+            .with_jitter(false);
+
+        assert_eq!(policy.backoff(66), DEFAULT_MAX_BACKOFF);
+        assert_eq!(policy.backoff(usize::MAX), DEFAULT_MAX_BACKOFF);
+        assert!(RetryPolicy::default().backoff(usize::MAX) <= DEFAULT_MAX_BACKOFF);
+    }
+
+    #[test]
+    fn test_waits_at_least_the_requested_delay() {
+        let policy = fast_policy().with_retry_after(|error| {
+            error
+                .to_string()
+                .contains("Slow down")
+                .then_some(Duration::from_secs(10))
+        });
+
+        assert_eq!(
+            policy.delay(1, &anyhow::anyhow!("Slow down")),
+            Duration::from_secs(10)
+        );
+        assert_eq!(
+            policy.delay(1, &anyhow::anyhow!("Unavailable")),
+            Duration::from_millis(1)
+        );
+    }
+
+    #[test]
+    fn test_clamps_invalid_multiplier() {
+        let policy = |multiplier| {
+            RetryPolicy::default()
+                .with_initial_backoff(Duration::from_secs(1))
+                .with_multiplier(multiplier)
+                .with_jitter(false)
+        };
+
+        assert_eq!(policy(f64::NAN).backoff(2), Duration::from_secs(2));
+        assert_eq!(policy(f64::INFINITY).backoff(2), Duration::from_secs(2));
+        assert_eq!(policy(-3.0).backoff(2), Duration::from_secs(1));
+        assert_eq!(policy(0.5).backoff(3), Duration::from_secs(1));
+    }
+}
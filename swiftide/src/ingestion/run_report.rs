@@ -0,0 +1,283 @@
+//! This module defines the `RunReport` returned by `IngestionPipeline::run`.
+//!
+//! Every stage of the pipeline records how many nodes it emitted, how many errors it produced and
+//! how much time was spent in it. When the run finishes, these statistics are collected into a
+//! `RunReport`, which can be used to gate CI jobs or alert on sudden drops in indexed nodes.
+//!
+//! Stages run concurrently, so the elapsed time of a stage is the wall-clock time from when it
+//! started its first task to when it finished its last one. The time spent in all of its tasks is
+//! reported separately as the busy time, which can exceed the elapsed time.
+
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+
+use super::{events::EventHandlers, IngestionNode, PipelineEvent};
+
+/// The kind of a pipeline stage, corresponding to the method that added it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum_macros::Display)]
+pub enum StageKind {
+    /// The loader the pipeline was created from.
+    Loader,
+    /// A checkpoint added with `with_checkpoint`.
+    Checkpoint,
+    /// A cache added with `filter_cached`.
+    Cache,
+    /// A transformer added with `then`.
+    Transformer,
+    /// A batch transformer added with `then_in_batch`.
+    BatchTransformer,
+    /// A chunker added with `then_chunk`.
+    Chunker,
+    /// A storage backend added with `then_store_with`.
+    Storage,
+}
+
+/// Statistics of a single stage of a pipeline run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StageReport {
+    /// Name of the stage, derived from the type of the loader, transformer or storage.
+    pub name: String,
+    /// The kind of the stage.
+    pub kind: StageKind,
+    /// Number of nodes emitted by the stage. For storage backends these are the stored nodes.
+    pub nodes: usize,
+    /// Number of nodes the stage skipped. Only checkpoints and caches skip nodes.
+    pub skipped: usize,
+    /// Number of errors produced by the stage.
+    pub errors: usize,
+    /// Wall-clock time from the start of the first task of the stage to the end of its last.
+    pub elapsed: Duration,
+    /// Time spent in the stage, summed over all concurrent tasks.
+    pub busy_time: Duration,
+}
+
+/// A report of a pipeline run, returned by `IngestionPipeline::run`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunReport {
+    /// Statistics for every stage, in the order the stages were added.
+    pub stages: Vec<StageReport>,
+    /// Number of nodes that made it through the whole pipeline.
+    pub total_nodes: usize,
+    /// Wall-clock time of the run.
+    pub elapsed: Duration,
+    /// Whether the run was cancelled before the loader was exhausted.
+    pub cancelled: bool,
+}
+
+impl RunReport {
+    /// Returns the stages of the given kind.
+    pub fn stages_of_kind(&self, kind: StageKind) -> impl Iterator<Item = &StageReport> {
+        self.stages.iter().filter(move |stage| stage.kind == kind)
+    }
+
+    /// Returns the number of nodes emitted by the loader.
+    pub fn nodes_loaded(&self) -> usize {
+        self.stages_of_kind(StageKind::Loader)
+            .map(|stage| stage.nodes)
+            .sum()
+    }
+
+    /// Returns the number of nodes skipped by all caches.
+    pub fn nodes_skipped_by_cache(&self) -> usize {
+        self.stages_of_kind(StageKind::Cache)
+            .map(|stage| stage.skipped)
+            .sum()
+    }
+
+    /// Returns the number of nodes skipped because they were completed in a previous run.
+    pub fn nodes_skipped_by_checkpoint(&self) -> usize {
+        self.stages_of_kind(StageKind::Checkpoint)
+            .map(|stage| stage.skipped)
+            .sum()
+    }
+
+    /// Returns the total number of errors over all stages.
+    pub fn errors(&self) -> usize {
+        self.stages.iter().map(|stage| stage.errors).sum()
+    }
+}
+
+/// Live statistics of a stage, updated concurrently while the pipeline runs.
+#[derive(Debug)]
+pub(crate) struct StageStats {
+    name: String,
+    kind: StageKind,
+    nodes: AtomicUsize,
+    skipped: AtomicUsize,
+    errors: AtomicUsize,
+    busy_nanos: AtomicU64,
+    /// The start of the first and the end of the last task
+    span: Mutex<Option<(Instant, Instant)>>,
+    events: Arc<EventHandlers>,
+}
+
+impl StageStats {
+    pub(crate) fn new(
+        kind: StageKind,
+        name: impl Into<String>,
+        events: Arc<EventHandlers>,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            name: name.into(),
+            kind,
+            nodes: AtomicUsize::new(0),
+            skipped: AtomicUsize::new(0),
+            errors: AtomicUsize::new(0),
+            busy_nanos: AtomicU64::new(0),
+            span: Mutex::new(None),
+            events,
+        })
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Records an emitted node or an error, depending on the result.
+    pub(crate) fn record(&self, result: &Result<IngestionNode>) {
+        match result {
+            Ok(node) => self.record_node(node),
+            Err(error) => self.record_error(error),
+        }
+    }
+
+    pub(crate) fn record_node(&self, node: &IngestionNode) {
+        self.nodes.fetch_add(1, Ordering::Relaxed);
+        self.events.emit(|| {
+            let stage = self.name.clone();
+            let path = node.path.clone();
+            match self.kind {
+                StageKind::Loader => PipelineEvent::NodeLoaded { stage, path },
+                StageKind::Storage => PipelineEvent::NodeStored { stage, path },
+                kind => PipelineEvent::NodeProcessed { stage, kind, path },
+            }
+        });
+    }
+
+    pub(crate) fn record_batch_stored(&self, count: usize) {
+        self.events.emit(|| PipelineEvent::BatchStored {
+            stage: self.name.clone(),
+            count,
+        });
+    }
+
+    pub(crate) fn record_error(&self, error: &anyhow::Error) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+        self.events.emit(|| PipelineEvent::Error {
+            stage: self.name.clone(),
+            kind: self.kind,
+            message: format!("{error:#}"),
+        });
+    }
+
+    pub(crate) fn record_skipped(&self, node: &IngestionNode) {
+        self.skipped.fetch_add(1, Ordering::Relaxed);
+        self.events.emit(|| PipelineEvent::NodeSkipped {
+            stage: self.name.clone(),
+            kind: self.kind,
+            path: node.path.clone(),
+        });
+    }
+
+    /// Records a task of the stage that started at `start` and ends now.
+    pub(crate) fn record_task(&self, start: Instant) {
+        let end = Instant::now();
+        self.busy_nanos.fetch_add(
+            u64::try_from(end.duration_since(start).as_nanos()).unwrap_or(u64::MAX),
+            Ordering::Relaxed,
+        );
+
+        let mut span = self.span.lock().unwrap_or_else(PoisonError::into_inner);
+        *span = Some(match *span {
+            Some((first_start, last_end)) => (first_start.min(start), last_end.max(end)),
+            None => (start, end),
+        });
+    }
+
+    /// Awaits the future and records the time it took.
+    pub(crate) async fn timed<F: Future>(&self, future: F) -> F::Output {
+        let start = Instant::now();
+        let output = future.await;
+        self.record_task(start);
+        output
+    }
+
+    pub(crate) fn report(&self) -> StageReport {
+        StageReport {
+            name: self.name.clone(),
+            kind: self.kind,
+            nodes: self.nodes.load(Ordering::Relaxed),
+            skipped: self.skipped.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            elapsed: self
+                .span
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .map_or(Duration::ZERO, |(first_start, last_end)| {
+                    last_end.duration_since(first_start)
+                }),
+            busy_time: Duration::from_nanos(self.busy_nanos.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// Returns the name of a stage from its type, without module paths.
+///
+/// I.e. `swiftide::transformers::chunk_code::ChunkCode` becomes `ChunkCode`.
+pub(crate) fn stage_name<T>() -> String {
+    let type_name = std::any::type_name::<T>();
+    let mut name = String::with_capacity(type_name.len());
+    let mut segment = String::new();
+
+    for c in type_name.chars() {
+        if c.is_alphanumeric() || c == '_' {
+            segment.push(c);
+        } else if c == ':' {
+            segment.clear();
+        } else {
+            name.push_str(&segment);
+            segment.clear();
+            name.push(c);
+        }
+    }
+    name.push_str(&segment);
+
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stage_name_strips_module_paths() {
+        assert_eq!(stage_name::<StageStats>(), "StageStats");
+        assert_eq!(
+            stage_name::<Option<std::path::PathBuf>>(),
+            "Option<PathBuf>"
+        );
+    }
+
+    #[test]
+    fn test_elapsed_is_wall_clock_time_of_concurrent_tasks() {
+        let stats = StageStats::new(StageKind::Transformer, "Stage", Arc::default());
+        let start = Instant::now() - Duration::from_millis(100);
+
+        // Two tasks that ran at the same time
+        stats.record_task(start);
+        stats.record_task(start);
+
+        let report = stats.report();
+        assert!(report.busy_time >= Duration::from_millis(200));
+        assert!(report.elapsed >= Duration::from_millis(100));
+        assert!(report.elapsed < report.busy_time);
+    }
+}
@@ -0,0 +1,94 @@
+//! This module defines the errors returned by the `Anthropic` integration.
+
+use std::{fmt, time::Duration};
+
+/// An error response from the Anthropic API.
+///
+/// Rate limits, overloaded servers and server errors are transient and can be retried, see
+/// `is_retryable` and `retry_after`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnthropicError {
+    /// The HTTP status of the response.
+    pub status: u16,
+    /// The type of the error, i.e. `rate_limit_error` or `overloaded_error`.
+    pub kind: String,
+    /// The message of the error.
+    pub message: String,
+    /// How long to wait before retrying, if the API returned a `retry-after` header.
+    pub retry_after: Option<Duration>,
+}
+
+impl AnthropicError {
+    /// Returns whether the request can be retried, i.e. after a rate limit or a server error.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self.status, 408 | 409 | 429 | 500..=599)
+            || matches!(
+                self.kind.as_str(),
+                "rate_limit_error" | "overloaded_error" | "api_error"
+            )
+    }
+}
+
+impl fmt::Display for AnthropicError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Anthropic returned {} ({}): {}",
+            self.status, self.kind, self.message
+        )
+    }
+}
+
+impl std::error::Error for AnthropicError {}
+
+/// Returns whether an error from the `Anthropic` integration can be retried.
+///
+/// Error responses are retried if `AnthropicError::is_retryable`, and requests that timed out or
+/// could not connect are retried as well. Use it with `RetryPolicy::with_retryable`.
+pub fn is_retryable(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        if let Some(error) = cause.downcast_ref::<AnthropicError>() {
+            error.is_retryable()
+        } else if let Some(error) = cause.downcast_ref::<reqwest::Error>() {
+            error.is_timeout() || error.is_connect()
+        } else {
+            false
+        }
+    })
+}
+
+/// Returns how long Anthropic asked to wait before retrying, if the error is an `AnthropicError`
+/// with a `retry-after` header. Use it with `RetryPolicy::with_retry_after`.
+pub fn retry_after(error: &anyhow::Error) -> Option<Duration> {
+    error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<AnthropicError>())
+        .and_then(|error| error.retry_after)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(status: u16, kind: &str) -> anyhow::Error {
+        AnthropicError {
+            status,
+            kind: kind.to_string(),
+            message: "message".to_string(),
+            retry_after: None,
+        }
+        .into()
+    }
+
+    #[test]
+    fn test_retryable_errors() {
+        assert!(is_retryable(&error(429, "rate_limit_error")));
+        assert!(is_retryable(&error(529, "overloaded_error")));
+        assert!(is_retryable(
+            &error(500, "api_error").context("Failed to prompt")
+        ));
+        assert!(!is_retryable(&error(400, "invalid_request_error")));
+        assert!(!is_retryable(&error(401, "authentication_error")));
+        assert!(!is_retryable(&anyhow::anyhow!("Model not set")));
+    }
+}
@@ -0,0 +1,146 @@
+//! This module provides integration with Anthropic's Messages API, enabling the use of Claude models within the
+//! Swiftide project.
+//! It includes the `Anthropic` struct for managing the API client and default options for prompts.
+//! The module is conditionally compiled based on the "anthropic" feature flag.
+
+use std::time::Duration;
+
+use derive_builder::Builder;
+
+use crate::rate_limit::RateLimiter;
+
+mod error;
+mod simple_prompt;
+
+pub use error::{is_retryable, retry_after, AnthropicError};
+
+const DEFAULT_BASE_URL: &str = "https://api.anthropic.com";
+const DEFAULT_MAX_TOKENS: u32 = 1024;
+const API_VERSION: &str = "2023-06-01";
+
+/// The `Anthropic` struct encapsulates an HTTP client for the Anthropic Messages API and default options for prompts.
+/// It uses the `Builder` pattern for flexible and customizable instantiation.
+///
+/// Errors are returned as `AnthropicError`, so transient errors such as rate limits and overloaded servers can be
+/// retried with `Retry`, waiting as long as the API asks to.
+///
+/// # Example
+///
+/// ```ignore
+/// let anthropic = Anthropic::builder()
+///     .default_prompt_model("claude-3-5-sonnet-20240620")
+///     .build()?;
+///
+/// let retry = RetryPolicy::default()
+///     .with_retryable(anthropic::is_retryable)
+///     .with_retry_after(anthropic::retry_after);
+/// pipeline.then(Retry::new(MetadataQACode::new(anthropic), retry))
+/// ```
+#[derive(Builder, Clone)]
+pub struct Anthropic {
+    /// The HTTP client, which is cheap to clone and shares its connection pool.
+    /// Defaults to a new instance of `reqwest::Client`.
+    #[builder(default)]
+    client: reqwest::Client,
+    /// The API key. Defaults to the `ANTHROPIC_API_KEY` environment variable.
+    #[builder(
+        default = "std::env::var(\"ANTHROPIC_API_KEY\").unwrap_or_default()",
+        setter(into)
+    )]
+    api_key: String,
+    /// The URL of the API. Defaults to `https://api.anthropic.com`.
+    #[builder(default = "DEFAULT_BASE_URL.to_string()", setter(into))]
+    base_url: String,
+    /// Default options for prompts.
+    #[builder(default)]
+    default_options: Options,
+    /// Optional rate limiter for all requests, shared by all clones of this instance.
+    #[builder(default, setter(strip_option))]
+    rate_limiter: Option<RateLimiter>,
+}
+
+/// The `Options` struct holds configuration options for the `Anthropic` client.
+#[derive(Debug, Clone, Builder)]
+#[builder(setter(into, strip_option))]
+pub struct Options {
+    /// The default prompt model to use, if specified.
+    #[builder(default)]
+    pub prompt_model: Option<String>,
+    /// The maximum number of tokens to generate. Defaults to 1024.
+    #[builder(default = "DEFAULT_MAX_TOKENS")]
+    pub max_tokens: u32,
+    /// The system prompt sent with every prompt, if specified.
+    #[builder(default)]
+    pub system_prompt: Option<String>,
+    /// The timeout of a request, if specified.
+    #[builder(default)]
+    pub timeout: Option<Duration>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            prompt_model: None,
+            max_tokens: DEFAULT_MAX_TOKENS,
+            system_prompt: None,
+            timeout: None,
+        }
+    }
+}
+
+impl Options {
+    /// Creates a new `OptionsBuilder` for constructing `Options` instances.
+    pub fn builder() -> OptionsBuilder {
+        OptionsBuilder::default()
+    }
+}
+
+impl Anthropic {
+    /// Creates a new `AnthropicBuilder` for constructing `Anthropic` instances.
+    pub fn builder() -> AnthropicBuilder {
+        AnthropicBuilder::default()
+    }
+}
+
+impl std::fmt::Debug for Anthropic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The api key is left out on purpose
+        f.debug_struct("Anthropic")
+            .field("base_url", &self.base_url)
+            .field("default_options", &self.default_options)
+            .field("rate_limiter", &self.rate_limiter)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AnthropicBuilder {
+    /// Sets the default prompt model for the `Anthropic` instance.
+    ///
+    /// # Parameters
+    /// - `model`: The prompt model to set.
+    ///
+    /// # Returns
+    /// A mutable reference to the `AnthropicBuilder`.
+    pub fn default_prompt_model(&mut self, model: impl Into<String>) -> &mut Self {
+        self.default_options
+            .get_or_insert_with(Options::default)
+            .prompt_model = Some(model.into());
+        self
+    }
+
+    /// Sets the default maximum number of tokens to generate.
+    pub fn default_max_tokens(&mut self, max_tokens: u32) -> &mut Self {
+        self.default_options
+            .get_or_insert_with(Options::default)
+            .max_tokens = max_tokens;
+        self
+    }
+
+    /// Sets the default system prompt.
+    pub fn default_system_prompt(&mut self, system_prompt: impl Into<String>) -> &mut Self {
+        self.default_options
+            .get_or_insert_with(Options::default)
+            .system_prompt = Some(system_prompt.into());
+        self
+    }
+}
@@ -0,0 +1,150 @@
+//! This module provides an implementation of the `SimplePrompt` trait for the `Anthropic` struct.
+//! It sends the prompt as a single user message to the Messages API and returns the text of the response.
+use std::time::Duration;
+
+use crate::SimplePrompt;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use super::{Anthropic, AnthropicError, API_VERSION};
+use anyhow::{Context as _, Result};
+
+#[derive(Debug, Serialize)]
+struct MessagesRequest<'a> {
+    model: &'a str,
+    max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<&'a str>,
+    messages: [Message<'a>; 1],
+}
+
+#[derive(Debug, Serialize)]
+struct Message<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessagesResponse {
+    content: Vec<ContentBlock>,
+    stop_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ContentBlock {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorResponse {
+    error: ErrorBody,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorBody {
+    #[serde(rename = "type")]
+    kind: String,
+    message: String,
+}
+
+#[async_trait]
+impl SimplePrompt for Anthropic {
+    /// Sends a prompt to the Anthropic Messages API and returns the text of the response.
+    ///
+    /// # Parameters
+    /// - `prompt`: A string slice that holds the prompt to be sent to the Anthropic API.
+    ///
+    /// # Returns
+    /// - `Result<String>`: On success, returns the text of the response as a `String`.
+    ///   On failure, returns an error wrapped in a `Result`.
+    ///
+    /// # Errors
+    /// - Returns an error if the model is not set in the default options.
+    /// - Returns an `AnthropicError` if the API responds with an error.
+    /// - Returns an error if the response does not contain any text.
+    #[tracing::instrument(skip(self), err)]
+    async fn prompt(&self, prompt: &str) -> Result<String> {
+        let options = &self.default_options;
+        let model = options.prompt_model.as_ref().context("Model not set")?;
+
+        let request = MessagesRequest {
+            model,
+            max_tokens: options.max_tokens,
+            system: options.system_prompt.as_deref(),
+            messages: [Message {
+                role: "user",
+                content: prompt,
+            }],
+        };
+        tracing::debug!(?request, "[SimplePrompt] Request to anthropic");
+
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.acquire_for(&[prompt]).await;
+        }
+
+        let mut builder = self
+            .client
+            .post(format!(
+                "{}/v1/messages",
+                self.base_url.trim_end_matches('/')
+            ))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", API_VERSION)
+            .json(&request);
+        if let Some(timeout) = options.timeout {
+            builder = builder.timeout(timeout);
+        }
+        let response = builder
+            .send()
+            .await
+            .context("Request to anthropic failed")?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let retry_after = response
+                .headers()
+                .get("retry-after")
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse().ok())
+                .map(Duration::from_secs);
+            let body = response.text().await.unwrap_or_default();
+            let (kind, message) = match serde_json::from_str::<ErrorResponse>(&body) {
+                Ok(ErrorResponse { error }) => (error.kind, error.message),
+                Err(_) => ("unknown".to_string(), body),
+            };
+
+            return Err(AnthropicError {
+                status: status.as_u16(),
+                kind,
+                message,
+                retry_after,
+            }
+            .into());
+        }
+
+        let response: MessagesResponse = response
+            .json()
+            .await
+            .context("Unexpected response from anthropic")?;
+        tracing::debug!(
+            stop_reason = response.stop_reason,
+            "[SimplePrompt] Response from anthropic"
+        );
+        if response.stop_reason.as_deref() == Some("max_tokens") {
+            tracing::warn!("Response was cut off at the maximum number of tokens");
+        }
+
+        let text = response
+            .content
+            .into_iter()
+            .filter(|block| block.kind == "text")
+            .map(|block| block.text)
+            .collect::<String>();
+        anyhow::ensure!(!text.is_empty(), "Expected text in response");
+
+        Ok(text)
+    }
+}
@@ -0,0 +1,363 @@
+//! This module provides integration with [Candle](https://github.com/huggingface/candle), to run
+//! sentence-embedding models locally on the CPU.
+//! It includes the `CandleEmbed` struct, which loads a BERT-based model and its tokenizer from disk
+//! and implements `Embed`, so no network access or API key is required.
+//! The module is conditionally compiled based on the "candle" feature flag.
+
+use std::{path::Path, sync::Arc};
+
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use candle_core::{DType, Device, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::{BertModel, Config};
+use tokenizers::{PaddingParams, PaddingStrategy, TruncationParams};
+
+use crate::{Embed, Embeddings, Tokenizer};
+
+const DEFAULT_BATCH_SIZE: usize = 32;
+
+/// How the embeddings of the tokens are pooled into the embedding of the input.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Pooling {
+    /// The mean of all tokens, as used by most sentence-transformers models.
+    #[default]
+    Mean,
+    /// The embedding of the first token, as used by i.e. BGE models.
+    Cls,
+}
+
+/// `CandleEmbed` runs a BERT-based sentence-embedding model on the CPU, loaded from local files.
+///
+/// The model is loaded from a directory with the files of a model on the Hugging Face hub:
+/// `config.json`, `tokenizer.json` and `model.safetensors`. The tokenizer of the model is used to
+/// keep inputs within its limit, see `TokenLimitPolicy`.
+///
+/// Models are cheap to clone and shared between clones.
+///
+/// # Example
+///
+/// ```ignore
+/// let model = CandleEmbed::try_from_dir("models/all-MiniLM-L6-v2")?;
+///
+/// pipeline.then_in_batch(32, GenericEmbed::new(model))
+/// ```
+#[derive(Clone)]
+pub struct CandleEmbed {
+    inner: Arc<Inner>,
+    pooling: Pooling,
+    normalize: bool,
+    batch_size: usize,
+}
+
+struct Inner {
+    model: BertModel,
+    /// Pads and truncates inputs to embed them in batches
+    batch_tokenizer: tokenizers::Tokenizer,
+    /// Counts tokens without truncating
+    tokenizer: tokenizers::Tokenizer,
+    max_tokens: usize,
+}
+
+impl CandleEmbed {
+    /// Loads a model from a directory with `config.json`, `tokenizer.json` and
+    /// `model.safetensors`.
+    pub fn try_from_dir(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        Self::try_from_files(
+            dir.join("config.json"),
+            dir.join("tokenizer.json"),
+            dir.join("model.safetensors"),
+        )
+    }
+
+    /// Loads a model from the given config, tokenizer and safetensors weights.
+    pub fn try_from_files(
+        config: impl AsRef<Path>,
+        tokenizer: impl AsRef<Path>,
+        weights: impl AsRef<Path>,
+    ) -> Result<Self> {
+        let (config, tokenizer, weights) = (config.as_ref(), tokenizer.as_ref(), weights.as_ref());
+
+        let config: Config = serde_json::from_str(
+            &std::fs::read_to_string(config)
+                .with_context(|| format!("Failed to read {}", config.display()))?,
+        )
+        .with_context(|| format!("Invalid model config {}", config.display()))?;
+
+        let tokenizer = tokenizers::Tokenizer::from_file(tokenizer)
+            .map_err(anyhow::Error::msg)
+            .with_context(|| format!("Failed to load tokenizer {}", tokenizer.display()))?;
+
+        let weights = std::fs::read(weights)
+            .with_context(|| format!("Failed to read {}", weights.display()))?;
+        let model = BertModel::load(
+            VarBuilder::from_buffered_safetensors(weights, DType::F32, &Device::Cpu)?,
+            &config,
+        )
+        .context("Failed to load model")?;
+
+        let mut tokenizer = tokenizer;
+        tokenizer
+            .with_padding(None)
+            .with_truncation(None)
+            .map_err(anyhow::Error::msg)?;
+
+        let mut batch_tokenizer = tokenizer.clone();
+        batch_tokenizer
+            .with_padding(Some(PaddingParams {
+                strategy: PaddingStrategy::BatchLongest,
+                pad_id: u32::try_from(config.pad_token_id)?,
+                ..Default::default()
+            }))
+            .with_truncation(Some(TruncationParams {
+                max_length: config.max_position_embeddings,
+                ..Default::default()
+            }))
+            .map_err(anyhow::Error::msg)?;
+
+        // The special tokens, i.e. [CLS] and [SEP], count towards the limit of the model
+        let special_tokens = tokenizer
+            .encode("", true)
+            .map_err(anyhow::Error::msg)?
+            .len();
+
+        Ok(Self {
+            inner: Arc::new(Inner {
+                model,
+                batch_tokenizer,
+                tokenizer,
+                max_tokens: config
+                    .max_position_embeddings
+                    .saturating_sub(special_tokens),
+            }),
+            pooling: Pooling::default(),
+            normalize: true,
+            batch_size: DEFAULT_BATCH_SIZE,
+        })
+    }
+
+    /// Sets how token embeddings are pooled. Defaults to `Pooling::Mean`.
+    pub fn with_pooling(mut self, pooling: Pooling) -> Self {
+        self.pooling = pooling;
+        self
+    }
+
+    /// Sets whether embeddings are normalized to unit length. Defaults to true.
+    pub fn with_normalize(mut self, normalize: bool) -> Self {
+        self.normalize = normalize;
+        self
+    }
+
+    /// Sets the number of inputs that are run through the model at once. Defaults to 32.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    fn embed_batch(&self, input: &[String]) -> Result<Embeddings> {
+        let encodings = self
+            .inner
+            .batch_tokenizer
+            .encode_batch(input.to_vec(), true)
+            .map_err(anyhow::Error::msg)?;
+
+        let tensor = |ids: Vec<&[u32]>| {
+            let shape = (ids.len(), ids.first().map_or(0, |ids| ids.len()));
+            Tensor::from_vec(ids.concat(), shape, &Device::Cpu)
+        };
+        let input_ids = tensor(encodings.iter().map(|e| e.get_ids()).collect())?;
+        let type_ids = tensor(encodings.iter().map(|e| e.get_type_ids()).collect())?;
+        let attention_mask = tensor(encodings.iter().map(|e| e.get_attention_mask()).collect())?;
+
+        // (batch, tokens, hidden)
+        let output = self
+            .inner
+            .model
+            .forward(&input_ids, &type_ids, Some(&attention_mask))?;
+
+        let embeddings = match self.pooling {
+            Pooling::Mean => {
+                let mask = attention_mask.to_dtype(DType::F32)?.unsqueeze(2)?;
+                output
+                    .broadcast_mul(&mask)?
+                    .sum(1)?
+                    .broadcast_div(&mask.sum(1)?)?
+            }
+            Pooling::Cls => output.narrow(1, 0, 1)?.squeeze(1)?,
+        };
+        let embeddings = if self.normalize {
+            embeddings.broadcast_div(&embeddings.sqr()?.sum_keepdim(1)?.sqrt()?)?
+        } else {
+            embeddings
+        };
+
+        Ok(embeddings.to_vec2()?)
+    }
+}
+
+impl std::fmt::Debug for CandleEmbed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CandleEmbed")
+            .field("max_tokens", &self.inner.max_tokens)
+            .field("pooling", &self.pooling)
+            .field("normalize", &self.normalize)
+            .field("batch_size", &self.batch_size)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl Embed for CandleEmbed {
+    #[tracing::instrument(skip_all, fields(inputs = input.len()), err)]
+    async fn embed(&self, input: Vec<String>) -> Result<Embeddings> {
+        let model = self.clone();
+
+        // Running the model is cpu bound and would block the runtime
+        tokio::task::spawn_blocking(move || {
+            let mut embeddings = Vec::with_capacity(input.len());
+            for batch in input.chunks(model.batch_size) {
+                embeddings.extend(model.embed_batch(batch)?);
+            }
+            Ok(embeddings)
+        })
+        .await?
+    }
+
+    fn max_tokens(&self) -> Option<usize> {
+        Some(self.inner.max_tokens)
+    }
+
+    fn tokenizer(&self) -> Option<Arc<dyn Tokenizer>> {
+        Some(Arc::new(self.clone()))
+    }
+}
+
+impl Tokenizer for CandleEmbed {
+    fn count_tokens(&self, text: &str) -> usize {
+        self.inner
+            .tokenizer
+            .encode_fast(text, false)
+            .map_or(0, |encoding| encoding.len())
+    }
+
+    fn truncate<'a>(&self, text: &'a str, max_tokens: usize) -> &'a str {
+        let Ok(encoding) = self.inner.tokenizer.encode(text, false) else {
+            return text;
+        };
+        if encoding.len() <= max_tokens {
+            return text;
+        }
+
+        let end = match max_tokens {
+            0 => 0,
+            _ => encoding.get_offsets()[max_tokens - 1].1,
+        };
+        // Offsets of tokens are byte offsets into the text, but keep on char boundaries anyway
+        let end = (0..=end)
+            .rev()
+            .find(|end| text.is_char_boundary(*end))
+            .unwrap_or(0);
+        &text[..end]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candle_nn::VarMap;
+    use temp_dir::TempDir;
+    use tokenizers::{models::wordlevel::WordLevel, pre_tokenizers::whitespace::Whitespace};
+
+    /// Writes a tiny randomly initialized model, so tests do not need to download one
+    fn tiny_model(dir: &Path) {
+        let config = serde_json::json!({
+            "vocab_size": 8,
+            "hidden_size": 8,
+            "num_hidden_layers": 1,
+            "num_attention_heads": 2,
+            "intermediate_size": 16,
+            "hidden_act": "gelu",
+            "hidden_dropout_prob": 0.1,
+            "max_position_embeddings": 16,
+            "type_vocab_size": 2,
+            "initializer_range": 0.02,
+            "layer_norm_eps": 1e-12,
+            "pad_token_id": 0
+        });
+        std::fs::write(dir.join("config.json"), config.to_string()).unwrap();
+
+        let varmap = VarMap::new();
+        BertModel::load(
+            VarBuilder::from_varmap(&varmap, DType::F32, &Device::Cpu),
+            &serde_json::from_value(config).unwrap(),
+        )
+        .unwrap();
+        varmap.save(dir.join("model.safetensors")).unwrap();
+
+        let vocab = ["[PAD]", "[UNK]", "fn", "main", "struct", "user", "let", "x"];
+        let model = WordLevel::builder()
+            .vocab(
+                vocab
+                    .iter()
+                    .enumerate()
+                    .map(|(id, token)| (token.to_string(), id as u32))
+                    .collect(),
+            )
+            .unk_token("[UNK]".to_string())
+            .build()
+            .unwrap();
+        let mut tokenizer = tokenizers::Tokenizer::new(model);
+        tokenizer.with_pre_tokenizer(Some(Whitespace {}));
+        tokenizer.save(dir.join("tokenizer.json"), false).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_embeds_normalized_in_batches() {
+        let dir = TempDir::new().unwrap();
+        tiny_model(dir.path());
+        let model = CandleEmbed::try_from_dir(dir.path())
+            .unwrap()
+            .with_batch_size(2);
+
+        let input = vec![
+            "fn main".to_string(),
+            "struct user".to_string(),
+            "let x x x x".to_string(),
+        ];
+        let embeddings = model.embed(input.clone()).await.unwrap();
+
+        assert_eq!(embeddings.len(), 3);
+        for embedding in &embeddings {
+            assert_eq!(embedding.len(), 8);
+            let norm = embedding.iter().map(|v| v * v).sum::<f32>().sqrt();
+            assert!((norm - 1.0).abs() < 1e-4);
+        }
+
+        // Padding in a batch does not change the embedding of an input
+        let single = model.embed(vec![input[0].clone()]).await.unwrap();
+        for (a, b) in single[0].iter().zip(&embeddings[0]) {
+            assert!((a - b).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_counts_and_truncates_tokens() {
+        let dir = TempDir::new().unwrap();
+        tiny_model(dir.path());
+        let model = CandleEmbed::try_from_dir(dir.path()).unwrap();
+
+        assert_eq!(model.max_tokens(), Some(16));
+        assert_eq!(model.count_tokens("let x x"), 3);
+        assert_eq!(model.truncate("let x x", 2), "let x");
+        assert_eq!(model.truncate("let x x", 5), "let x x");
+    }
+
+    #[test]
+    fn test_missing_files() {
+        let dir = TempDir::new().unwrap();
+        let error = CandleEmbed::try_from_dir(dir.path()).unwrap_err();
+
+        assert!(error.to_string().contains("config.json"));
+    }
+}
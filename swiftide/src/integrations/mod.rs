@@ -1,3 +1,9 @@
+#[cfg(feature = "anthropic")]
+pub mod anthropic;
+#[cfg(feature = "candle")]
+pub mod candle;
+#[cfg(feature = "ollama")]
+pub mod ollama;
 #[cfg(feature = "openai")]
 pub mod openai;
 #[cfg(feature = "qdrant")]
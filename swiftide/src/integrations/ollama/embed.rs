@@ -0,0 +1,54 @@
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{Embed, Embeddings};
+
+use super::Ollama;
+
+#[derive(Debug, Serialize)]
+struct EmbedRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbedResponse {
+    embeddings: Embeddings,
+}
+
+#[async_trait]
+impl Embed for Ollama {
+    async fn embed(&self, input: Vec<String>) -> Result<Embeddings> {
+        let model = self
+            .default_options
+            .embed_model
+            .as_ref()
+            .context("Model not set")?;
+
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.acquire_for(&input).await;
+        }
+
+        tracing::debug!(model, inputs = input.len(), "[Embed] Request to ollama");
+        let response: EmbedResponse = self
+            .post(
+                "api/embed",
+                &EmbedRequest {
+                    model,
+                    input: &input,
+                },
+            )
+            .await?;
+        tracing::debug!("[Embed] Response ollama");
+
+        // Embeddings are returned in the order of the input
+        anyhow::ensure!(
+            response.embeddings.len() == input.len(),
+            "Expected {} embeddings from Ollama, got {}",
+            input.len(),
+            response.embeddings.len()
+        );
+        Ok(response.embeddings)
+    }
+}
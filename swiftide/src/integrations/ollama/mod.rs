@@ -0,0 +1,137 @@
+//! This module provides integration with Ollama's HTTP API, enabling the use of locally running language models and
+//! embeddings within the Swiftide project.
+//! It includes the `Ollama` struct for managing the API client and default options for embedding and prompt models.
+//! The module is conditionally compiled based on the "ollama" feature flag.
+
+use anyhow::{Context as _, Result};
+use derive_builder::Builder;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::rate_limit::RateLimiter;
+
+mod embed;
+mod simple_prompt;
+
+const DEFAULT_BASE_URL: &str = "http://localhost:11434";
+
+/// The `Ollama` struct encapsulates an HTTP client for an Ollama server and default options for embedding and prompt
+/// models. It uses the `Builder` pattern for flexible and customizable instantiation.
+///
+/// # Example
+///
+/// ```ignore
+/// let ollama = Ollama::builder()
+///     .default_prompt_model("llama3")
+///     .default_embed_model("nomic-embed-text")
+///     .build()?;
+/// ```
+#[derive(Debug, Builder, Clone)]
+pub struct Ollama {
+    /// The HTTP client, which is cheap to clone and shares its connection pool.
+    /// Defaults to a new instance of `reqwest::Client`.
+    #[builder(default)]
+    client: reqwest::Client,
+    /// The URL of the Ollama server. Defaults to `http://localhost:11434`.
+    #[builder(default = "DEFAULT_BASE_URL.to_string()", setter(into))]
+    base_url: String,
+    /// Default options for embedding and prompt models.
+    #[builder(default)]
+    default_options: Options,
+    /// Optional rate limiter for all requests, shared by all clones of this instance.
+    #[builder(default, setter(strip_option))]
+    rate_limiter: Option<RateLimiter>,
+}
+
+/// The `Options` struct holds configuration options for the `Ollama` client.
+/// It includes optional fields for specifying the embedding and prompt models.
+#[derive(Debug, Default, Clone, Builder)]
+#[builder(setter(into, strip_option))]
+pub struct Options {
+    /// The default embedding model to use, if specified.
+    #[builder(default)]
+    pub embed_model: Option<String>,
+    /// The default prompt model to use, if specified.
+    #[builder(default)]
+    pub prompt_model: Option<String>,
+}
+
+impl Options {
+    /// Creates a new `OptionsBuilder` for constructing `Options` instances.
+    pub fn builder() -> OptionsBuilder {
+        OptionsBuilder::default()
+    }
+}
+
+impl Ollama {
+    /// Creates a new `OllamaBuilder` for constructing `Ollama` instances.
+    pub fn builder() -> OllamaBuilder {
+        OllamaBuilder::default()
+    }
+
+    /// Sends a request to an endpoint of the Ollama API and deserializes the response.
+    ///
+    /// # Errors
+    /// Returns an error if the request fails, if Ollama responds with an error status, or if the
+    /// response cannot be deserialized.
+    async fn post<T: DeserializeOwned>(&self, endpoint: &str, body: &impl Serialize) -> Result<T> {
+        let url = format!("{}/{}", self.base_url.trim_end_matches('/'), endpoint);
+        let response = self
+            .client
+            .post(&url)
+            .json(body)
+            .send()
+            .await
+            .with_context(|| format!("Request to {url} failed"))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Ollama returned {status} for {url}: {body}");
+        }
+
+        response
+            .json()
+            .await
+            .with_context(|| format!("Unexpected response from {url}"))
+    }
+}
+
+impl OllamaBuilder {
+    /// Sets the default embedding model for the `Ollama` instance.
+    ///
+    /// # Parameters
+    /// - `model`: The embedding model to set.
+    ///
+    /// # Returns
+    /// A mutable reference to the `OllamaBuilder`.
+    pub fn default_embed_model(&mut self, model: impl Into<String>) -> &mut Self {
+        if let Some(options) = self.default_options.as_mut() {
+            options.embed_model = Some(model.into());
+        } else {
+            self.default_options = Some(Options {
+                embed_model: Some(model.into()),
+                ..Default::default()
+            });
+        }
+        self
+    }
+
+    /// Sets the default prompt model for the `Ollama` instance.
+    ///
+    /// # Parameters
+    /// - `model`: The prompt model to set.
+    ///
+    /// # Returns
+    /// A mutable reference to the `OllamaBuilder`.
+    pub fn default_prompt_model(&mut self, model: impl Into<String>) -> &mut Self {
+        if let Some(options) = self.default_options.as_mut() {
+            options.prompt_model = Some(model.into());
+        } else {
+            self.default_options = Some(Options {
+                prompt_model: Some(model.into()),
+                ..Default::default()
+            });
+        }
+        self
+    }
+}
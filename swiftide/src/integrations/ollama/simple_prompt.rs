@@ -0,0 +1,74 @@
+//! This module provides an implementation of the `SimplePrompt` trait for the `Ollama` struct.
+//! It sends the prompt as a single user message to the chat endpoint of the Ollama API.
+use crate::SimplePrompt;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use super::Ollama;
+use anyhow::{Context as _, Result};
+
+#[derive(Debug, Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: [Message<'a>; 1],
+    stream: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct Message<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatResponse {
+    message: ResponseMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponseMessage {
+    content: String,
+}
+
+#[async_trait]
+impl SimplePrompt for Ollama {
+    /// Sends a prompt to the Ollama API and returns the response content.
+    ///
+    /// # Parameters
+    /// - `prompt`: A string slice that holds the prompt to be sent to the Ollama API.
+    ///
+    /// # Returns
+    /// - `Result<String>`: On success, returns the content of the response as a `String`.
+    ///   On failure, returns an error wrapped in a `Result`.
+    ///
+    /// # Errors
+    /// - Returns an error if the model is not set in the default options.
+    /// - Returns an error if the request to the Ollama API fails.
+    #[tracing::instrument(skip(self), err)]
+    async fn prompt(&self, prompt: &str) -> Result<String> {
+        let model = self
+            .default_options
+            .prompt_model
+            .as_ref()
+            .context("Model not set")?;
+
+        let request = ChatRequest {
+            model,
+            messages: [Message {
+                role: "user",
+                content: prompt,
+            }],
+            stream: false,
+        };
+        tracing::debug!(?request, "[SimplePrompt] Request to ollama");
+
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.acquire_for(&[prompt]).await;
+        }
+
+        let response: ChatResponse = self.post("api/chat", &request).await?;
+        tracing::debug!("[SimplePrompt] Response from ollama");
+
+        Ok(response.message.content)
+    }
+}
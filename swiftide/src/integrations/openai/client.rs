@@ -0,0 +1,156 @@
+//! This module defines the client used by the `OpenAI` struct and the options to build it.
+//!
+//! The client talks to the OpenAI API, any server with an OpenAI compatible API, such as vLLM,
+//! llama.cpp server or LiteLLM, or to an Azure OpenAI deployment.
+
+use std::time::Duration;
+
+use async_openai::{
+    config::{AzureConfig, OpenAIConfig},
+    error::OpenAIError,
+    types::{
+        CreateChatCompletionRequest, CreateChatCompletionResponse, CreateEmbeddingRequest,
+        CreateEmbeddingResponse,
+    },
+};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+
+/// An async-openai client for either the OpenAI API or Azure OpenAI.
+#[derive(Debug, Clone)]
+pub(super) enum Client {
+    OpenAI(async_openai::Client<OpenAIConfig>),
+    Azure(async_openai::Client<AzureConfig>),
+}
+
+impl Client {
+    pub(super) async fn create_chat_completion(
+        &self,
+        request: CreateChatCompletionRequest,
+    ) -> Result<CreateChatCompletionResponse, OpenAIError> {
+        match self {
+            Client::OpenAI(client) => client.chat().create(request).await,
+            Client::Azure(client) => client.chat().create(request).await,
+        }
+    }
+
+    pub(super) async fn create_embeddings(
+        &self,
+        request: CreateEmbeddingRequest,
+    ) -> Result<CreateEmbeddingResponse, OpenAIError> {
+        match self {
+            Client::OpenAI(client) => client.embeddings().create(request).await,
+            Client::Azure(client) => client.embeddings().create(request).await,
+        }
+    }
+}
+
+/// The options set on the `OpenAIBuilder` to build the client with.
+#[derive(Debug, Clone, Default)]
+pub(super) struct ClientOptions {
+    pub(super) client: Option<async_openai::Client<OpenAIConfig>>,
+    pub(super) api_base: Option<String>,
+    pub(super) api_key: Option<String>,
+    pub(super) organization: Option<String>,
+    pub(super) headers: Vec<(String, String)>,
+    pub(super) timeout: Option<Duration>,
+    pub(super) azure: Option<AzureOptions>,
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct AzureOptions {
+    pub(super) deployment_id: String,
+    pub(super) api_version: String,
+}
+
+impl ClientOptions {
+    /// Builds the client. A client set with `OpenAIBuilder::client` is used as is, so it cannot be
+    /// combined with any other client option.
+    pub(super) fn build(&self) -> Result<Client, String> {
+        if let Some(client) = &self.client {
+            let ignored = [
+                ("api_base", self.api_base.is_some()),
+                ("api_key", self.api_key.is_some()),
+                ("organization", self.organization.is_some()),
+                ("header", !self.headers.is_empty()),
+                ("timeout", self.timeout.is_some()),
+                ("azure_deployment", self.azure.is_some()),
+            ]
+            .into_iter()
+            .filter_map(|(option, is_set)| is_set.then_some(option))
+            .collect::<Vec<_>>();
+
+            if !ignored.is_empty() {
+                return Err(format!(
+                    "A client cannot be combined with {}, configure the client instead",
+                    ignored.join(", ")
+                ));
+            }
+            return Ok(Client::OpenAI(client.clone()));
+        }
+
+        let http_client = self.http_client()?;
+
+        if let Some(azure) = &self.azure {
+            let api_base = self
+                .api_base
+                .as_ref()
+                .ok_or("Azure OpenAI requires an api base")?;
+            let mut config = AzureConfig::new()
+                .with_api_base(api_base)
+                .with_deployment_id(&azure.deployment_id)
+                .with_api_version(&azure.api_version);
+            if let Some(api_key) = &self.api_key {
+                config = config.with_api_key(api_key);
+            }
+
+            let client = async_openai::Client::with_config(config);
+            return Ok(Client::Azure(match http_client {
+                Some(http_client) => client.with_http_client(http_client),
+                None => client,
+            }));
+        }
+
+        let mut config = OpenAIConfig::new();
+        if let Some(api_base) = &self.api_base {
+            config = config.with_api_base(api_base);
+        }
+        if let Some(api_key) = &self.api_key {
+            config = config.with_api_key(api_key);
+        }
+        if let Some(organization) = &self.organization {
+            config = config.with_org_id(organization);
+        }
+
+        let client = async_openai::Client::with_config(config);
+        Ok(Client::OpenAI(match http_client {
+            Some(http_client) => client.with_http_client(http_client),
+            None => client,
+        }))
+    }
+
+    /// Builds an HTTP client with the custom headers and timeout, if any are set.
+    fn http_client(&self) -> Result<Option<reqwest::Client>, String> {
+        if self.headers.is_empty() && self.timeout.is_none() {
+            return Ok(None);
+        }
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let name = HeaderName::try_from(name.as_str())
+                .map_err(|e| format!("Invalid header name {name}: {e}"))?;
+            let value = HeaderValue::try_from(value.as_str())
+                .map_err(|e| format!("Invalid value for header {name}: {e}"))?;
+            headers.insert(name, value);
+        }
+
+        let mut builder = reqwest::Client::builder().default_headers(headers);
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+
+        builder
+            .build()
+            .map(Some)
+            .map_err(|e| format!("Failed to build HTTP client: {e}"))
+    }
+}
@@ -1,10 +1,12 @@
+use std::sync::Arc;
+
 use anyhow::{Context as _, Result};
-use async_openai::types::CreateEmbeddingRequestArgs;
+use async_openai::types::{CreateEmbeddingRequestArgs, Embedding};
 use async_trait::async_trait;
 
-use crate::{Embed, Embeddings};
+use crate::{Embed, Embeddings, Tokenizer};
 
-use super::OpenAI;
+use super::{OpenAI, TiktokenTokenizer};
 
 #[async_trait]
 impl Embed for OpenAI {
@@ -15,6 +17,11 @@ impl Embed for OpenAI {
             .as_ref()
             .context("Model not set")?;
 
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.acquire_for(&input).await;
+        }
+
+        let expected = input.len();
         let request = CreateEmbeddingRequestArgs::default()
             .model(model)
             .input(input)
@@ -23,10 +30,111 @@ impl Embed for OpenAI {
             messages = serde_json::to_string_pretty(&request)?,
             "[Embed] Request to openai"
         );
-        let response = self.client.embeddings().create(request).await?;
+        let response = self.client.create_embeddings(request).await?;
         tracing::debug!("[Embed] Response openai");
 
-        // WARN: Naively assumes that the order is preserved. Might not always be the case.
-        Ok(response.data.into_iter().map(|d| d.embedding).collect())
+        order_by_index(response.data, expected)
+    }
+
+    /// All current OpenAI embedding models accept 8191 tokens per input.
+    fn max_tokens(&self) -> Option<usize> {
+        self.has_known_embed_model().then_some(8191)
+    }
+
+    /// All current OpenAI embedding models use the `cl100k_base` encoding.
+    fn tokenizer(&self) -> Option<Arc<dyn Tokenizer>> {
+        self.has_known_embed_model()
+            .then(|| Arc::new(TiktokenTokenizer::cl100k_base()) as Arc<dyn Tokenizer>)
+    }
+}
+
+impl OpenAI {
+    fn has_known_embed_model(&self) -> bool {
+        matches!(
+            self.default_options.embed_model.as_deref(),
+            Some("text-embedding-3-small" | "text-embedding-3-large" | "text-embedding-ada-002")
+        )
+    }
+}
+
+/// Orders the embeddings by the index of their input.
+///
+/// The response is not guaranteed to be in the order of the input, so every embedding is placed
+/// at its returned index. Any missing, duplicate or out of range index is an error, instead of
+/// attaching an embedding to the wrong input.
+fn order_by_index(data: Vec<Embedding>, expected: usize) -> Result<Embeddings> {
+    anyhow::ensure!(
+        data.len() == expected,
+        "Expected {expected} embeddings from OpenAI, got {}",
+        data.len()
+    );
+
+    let mut embeddings: Vec<Option<Vec<f32>>> = vec![None; expected];
+    for embedding in data {
+        let index = embedding.index as usize;
+        let slot = embeddings.get_mut(index).with_context(|| {
+            format!("OpenAI returned an embedding for index {index}, but only {expected} inputs were sent")
+        })?;
+        anyhow::ensure!(
+            slot.is_none(),
+            "OpenAI returned multiple embeddings for index {index}"
+        );
+        *slot = Some(embedding.embedding);
+    }
+
+    embeddings
+        .into_iter()
+        .enumerate()
+        .map(|(index, embedding)| {
+            embedding.with_context(|| format!("OpenAI returned no embedding for index {index}"))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn embedding(index: u32) -> Embedding {
+        Embedding {
+            index,
+            object: "embedding".to_string(),
+            embedding: vec![index as f32],
+        }
+    }
+
+    #[test]
+    fn test_orders_by_index() {
+        let embeddings = order_by_index(vec![embedding(2), embedding(0), embedding(1)], 3).unwrap();
+
+        assert_eq!(embeddings, vec![vec![0.0], vec![1.0], vec![2.0]]);
+    }
+
+    #[test]
+    fn test_mismatches_are_errors() {
+        let error = |data, expected| order_by_index(data, expected).unwrap_err().to_string();
+
+        assert!(error(vec![embedding(0)], 2).contains("Expected 2 embeddings"));
+        assert!(error(vec![embedding(0), embedding(0)], 2).contains("multiple embeddings"));
+        assert!(error(vec![embedding(0), embedding(5)], 2).contains("index 5"));
+    }
+
+    #[test]
+    fn test_known_models_provide_limit_and_tokenizer() {
+        let openai = |model: &str| {
+            OpenAI::builder()
+                .api_key("test")
+                .default_embed_model(model)
+                .build()
+                .unwrap()
+        };
+
+        let known = openai("text-embedding-3-small");
+        assert_eq!(known.max_tokens(), Some(8191));
+        assert_eq!(known.tokenizer().unwrap().count_tokens("hello world"), 2);
+
+        let unknown = openai("nomic-embed-text");
+        assert_eq!(unknown.max_tokens(), None);
+        assert!(unknown.tokenizer().is_none());
     }
 }
@@ -3,22 +3,49 @@
 //! The module is conditionally compiled based on the "openai" feature flag.
 
 use derive_builder::Builder;
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
+use crate::rate_limit::RateLimiter;
+
+mod client;
 mod embed;
 mod simple_prompt;
+mod tokenizer;
+
+use client::{AzureOptions, Client, ClientOptions};
+pub use tokenizer::TiktokenTokenizer;
 
 /// The `OpenAI` struct encapsulates an OpenAI client and default options for embedding and prompt models.
 /// It uses the `Builder` pattern for flexible and customizable instantiation.
+///
+/// Besides the OpenAI API, it can talk to any server with an OpenAI compatible API by setting its
+/// `api_base`, or to Azure OpenAI with `azure_deployment`.
+///
+/// # Example
+///
+/// ```ignore
+/// let openai = OpenAI::builder()
+///     .api_base("http://localhost:8000/v1")
+///     .header("X-Team", "search")
+///     .timeout(Duration::from_secs(30))
+///     .default_prompt_model("meta-llama/Meta-Llama-3-8B-Instruct")
+///     .build()?;
+/// ```
 #[derive(Debug, Builder, Clone)]
 pub struct OpenAI {
     /// The OpenAI client, wrapped in an `Arc` for thread-safe reference counting.
-    /// Defaults to a new instance of `async_openai::Client`.
-    #[builder(default = "Arc::new(async_openai::Client::new())", setter(custom))]
-    client: Arc<async_openai::Client<async_openai::config::OpenAIConfig>>,
+    /// Defaults to a new client, configured by the builder.
+    #[builder(
+        setter(custom),
+        field(ty = "ClientOptions", build = "Arc::new(self.client.build()?)")
+    )]
+    client: Arc<Client>,
     /// Default options for embedding and prompt models.
     #[builder(default)]
     default_options: Options,
+    /// Optional rate limiter for all requests, shared by all clones of this instance.
+    #[builder(default, setter(strip_option))]
+    rate_limiter: Option<RateLimiter>,
 }
 
 /// The `Options` struct holds configuration options for the `OpenAI` client.
@@ -51,6 +78,9 @@ impl OpenAI {
 impl OpenAIBuilder {
     /// Sets the OpenAI client for the `OpenAI` instance.
     ///
+    /// The client is used as is, combining it with any other client option, such as `api_base` or
+    /// `header`, fails the build.
+    ///
     /// # Parameters
     /// - `client`: The OpenAI client to set.
     ///
@@ -60,7 +90,59 @@ impl OpenAIBuilder {
         &mut self,
         client: async_openai::Client<async_openai::config::OpenAIConfig>,
     ) -> &mut Self {
-        self.client = Some(Arc::new(client));
+        self.client.client = Some(client);
+        self
+    }
+
+    /// Sets the base URL of the API, i.e. of an OpenAI compatible server such as vLLM or a
+    /// LiteLLM proxy, or of the Azure OpenAI resource.
+    ///
+    /// Defaults to `https://api.openai.com/v1`.
+    pub fn api_base(&mut self, api_base: impl Into<String>) -> &mut Self {
+        self.client.api_base = Some(api_base.into());
+        self
+    }
+
+    /// Sets the API key. Defaults to the `OPENAI_API_KEY` environment variable.
+    pub fn api_key(&mut self, api_key: impl Into<String>) -> &mut Self {
+        self.client.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Sets the OpenAI organization to send requests for.
+    pub fn organization(&mut self, organization: impl Into<String>) -> &mut Self {
+        self.client.organization = Some(organization.into());
+        self
+    }
+
+    /// Adds a header that is sent with every request. Invalid headers fail the build.
+    pub fn header(&mut self, name: impl Into<String>, value: impl Into<String>) -> &mut Self {
+        self.client.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Sets the timeout of every request.
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.client.timeout = Some(timeout);
+        self
+    }
+
+    /// Talks to an Azure OpenAI deployment, at the resource set with `api_base`.
+    ///
+    /// The model names of the default options are sent as is, the deployment determines the model.
+    ///
+    /// # Parameters
+    /// - `deployment_id`: The name of the deployment.
+    /// - `api_version`: The API version, i.e. `2024-02-01`.
+    pub fn azure_deployment(
+        &mut self,
+        deployment_id: impl Into<String>,
+        api_version: impl Into<String>,
+    ) -> &mut Self {
+        self.client.azure = Some(AzureOptions {
+            deployment_id: deployment_id.into(),
+            api_version: api_version.into(),
+        });
         self
     }
 
@@ -48,8 +48,13 @@ impl SimplePrompt for OpenAI {
             "[SimplePrompt] Request to openai"
         );
 
+        // Wait for the rate limiter, if any, before sending the request.
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.acquire_for(&[prompt]).await;
+        }
+
         // Send the request to the OpenAI API and await the response.
-        let mut response = self.client.chat().create(request).await?;
+        let mut response = self.client.create_chat_completion(request).await?;
 
         // Log the response for debugging purposes.
         tracing::debug!(
@@ -0,0 +1,94 @@
+//! This module provides the exact tokenizer of the OpenAI embedding models.
+
+use std::sync::{Arc, OnceLock};
+
+use tiktoken_rs::CoreBPE;
+
+use crate::Tokenizer;
+
+/// Counts and truncates text with a tiktoken encoding, as used by OpenAI models.
+///
+/// The encoding is loaded once and shared between all instances.
+#[derive(Clone)]
+pub struct TiktokenTokenizer {
+    bpe: Arc<CoreBPE>,
+}
+
+impl TiktokenTokenizer {
+    /// Creates a tokenizer with the `cl100k_base` encoding, used by the `text-embedding-3` and
+    /// `text-embedding-ada-002` models.
+    pub fn cl100k_base() -> Self {
+        static CL100K_BASE: OnceLock<Arc<CoreBPE>> = OnceLock::new();
+
+        Self {
+            bpe: CL100K_BASE
+                .get_or_init(|| {
+                    Arc::new(tiktoken_rs::cl100k_base().expect("Bundled encoding is valid"))
+                })
+                .clone(),
+        }
+    }
+}
+
+impl std::fmt::Debug for TiktokenTokenizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TiktokenTokenizer").finish_non_exhaustive()
+    }
+}
+
+impl Tokenizer for TiktokenTokenizer {
+    fn count_tokens(&self, text: &str) -> usize {
+        self.bpe.encode_ordinary(text).len()
+    }
+
+    fn truncate<'a>(&self, text: &'a str, max_tokens: usize) -> &'a str {
+        let tokens = self.bpe.encode_ordinary(text);
+        if tokens.len() <= max_tokens {
+            return text;
+        }
+
+        // Tokens decode to the exact bytes of the text, but a token can end within a character, in
+        // which case decoding fails and the prefix is trimmed to the previous token until it does not
+        let end = (1..=max_tokens)
+            .rev()
+            .find_map(|count| self.bpe.decode(tokens[..count].to_vec()).ok())
+            .map_or(0, |prefix| prefix.len());
+        let end = (0..=end)
+            .rev()
+            .find(|end| text.is_char_boundary(*end))
+            .unwrap_or(0);
+
+        // A single character can span multiple tokens, keep it rather than returning nothing
+        if end == 0 && max_tokens > 0 {
+            return text
+                .char_indices()
+                .nth(1)
+                .map_or(text, |(end, _)| &text[..end]);
+        }
+        &text[..end]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_and_truncates_exact_tokens() {
+        let tokenizer = TiktokenTokenizer::cl100k_base();
+
+        assert_eq!(tokenizer.count_tokens("hello world"), 2);
+        assert_eq!(tokenizer.truncate("hello world", 1), "hello");
+        assert_eq!(tokenizer.truncate("hello world", 2), "hello world");
+
+        // Characters outside of ASCII often take more than one token each
+        let text = "日本語のテキスト";
+        assert!(tokenizer.count_tokens(text) > text.chars().count() / 4);
+        let truncated = tokenizer.truncate(text, 2);
+        assert!(!truncated.is_empty() && tokenizer.count_tokens(truncated) <= 2);
+
+        // A token ending within a character does not decode, the character is kept whole
+        assert!(tokenizer.count_tokens("🦀") > 1);
+        assert_eq!(tokenizer.truncate("🦀🦀", 1), "🦀");
+    }
+}
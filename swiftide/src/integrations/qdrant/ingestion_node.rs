@@ -1,4 +1,5 @@
-//! This module provides functionality to convert an `IngestionNode` into a `qdrant::PointStruct`.
+//! This module provides functionality to convert an `IngestionNode` into a `qdrant::PointStruct`,
+//! and a `qdrant::ScoredPoint` back into a `ScoredNode`.
 //! The conversion is essential for storing data in the Qdrant vector database, which is used
 //! for efficient vector similarity search. The module handles metadata augmentation and ensures
 //! data compatibility with Qdrant's required format.
@@ -6,12 +7,27 @@
 use anyhow::{Context as _, Result};
 use std::collections::HashMap;
 
-use crate::ingestion::IngestionNode;
+use crate::{
+    ingestion::{IngestionNode, Lineage},
+    query::ScoredNode,
+};
 use qdrant_client::{
     client::Payload,
-    qdrant::{self, Value},
+    qdrant::{self, point_id::PointIdOptions, Value},
 };
 
+/// Payload keys used for the fields of the node, which node metadata cannot use.
+const RESERVED_PAYLOAD_KEYS: [&str; 8] = [
+    "path",
+    "content",
+    "hash_version",
+    "last_updated_at",
+    "source_range",
+    "parent_id",
+    "chunk_index",
+    "sibling_count",
+];
+
 /// Implements the `TryInto` trait to convert an `IngestionNode` into a `qdrant::PointStruct`.
 /// This conversion is necessary for storing the node in the Qdrant vector database.
 impl TryInto<qdrant::PointStruct> for IngestionNode {
@@ -21,39 +37,314 @@ impl TryInto<qdrant::PointStruct> for IngestionNode {
     ///
     /// # Errors
     ///
-    /// Returns an error if the vector is not set in the `IngestionNode`.
+    /// Returns an error if none of the vector, named vectors or sparse vectors are set in the
+    /// `IngestionNode`, if both the vector and named vectors are set, or if the metadata contains
+    /// a key the payload reserves for the fields of the node, such as `path` or `parent_id`.
     ///
     /// # Returns
     ///
     /// A `Result` which is `Ok` if the conversion is successful, containing the `qdrant::PointStruct`.
     /// If the conversion fails, it returns an `anyhow::Error`.
     fn try_into(mut self) -> Result<qdrant::PointStruct> {
+        if let Some(key) = RESERVED_PAYLOAD_KEYS
+            .iter()
+            .find(|key| self.metadata.contains_key(**key))
+        {
+            anyhow::bail!("Metadata key `{key}` is reserved in the Qdrant payload");
+        }
+
         // Calculate a unique identifier for the node.
         let id = self.calculate_hash();
 
         // Extend the metadata with additional information.
         self.metadata.extend([
-            ("path".to_string(), self.path.to_string_lossy().to_string()),
-            ("content".to_string(), self.chunk),
+            ("path".to_string(), self.path.to_string_lossy().into()),
+            ("content".to_string(), self.chunk.into()),
+            (
+                "hash_version".to_string(),
+                IngestionNode::HASH_VERSION.into(),
+            ),
             (
                 "last_updated_at".to_string(),
-                chrono::Utc::now().to_rfc3339(),
+                chrono::Utc::now().to_rfc3339().into(),
             ),
         ]);
+        if let Some(source_range) = &self.source_range {
+            self.metadata.insert(
+                "source_range".to_string(),
+                serde_json::to_value(source_range)?,
+            );
+        }
+        // Qdrant stores integers as signed 64 bit, so the parent id is stored as a string
+        if let Some(lineage) = &self.lineage {
+            self.metadata.extend([
+                (
+                    "parent_id".to_string(),
+                    lineage.parent_id.to_string().into(),
+                ),
+                ("chunk_index".to_string(), lineage.chunk_index.into()),
+                ("sibling_count".to_string(), lineage.sibling_count.into()),
+            ]);
+        }
 
-        // Create a payload compatible with Qdrant's API.
+        // Create a payload compatible with Qdrant's API, keeping the types of the metadata.
         let payload: Payload = self
             .metadata
             .iter()
-            .map(|(k, v)| (k.as_str(), Value::from(v.as_str())))
+            .map(|(k, v)| (k.as_str(), Value::from(v.clone())))
             .collect::<HashMap<&str, Value>>()
             .into();
 
+        // Named vectors are stored as such, a collection cannot mix them with an unnamed vector
+        if self.vector.is_some() && !self.vectors.is_empty() {
+            anyhow::bail!("Node has both an unnamed vector and named vectors");
+        }
+        if self.vector.is_none() && self.vectors.is_empty() && self.sparse_vectors.is_empty() {
+            anyhow::bail!("Vector is not set");
+        }
+
+        let vectors: qdrant::Vectors = match self.vector {
+            Some(vector) if self.sparse_vectors.is_empty() => vector.into(),
+            vector => {
+                // Sparse vectors are always named, the unnamed vector is named "" alongside them
+                let mut named: HashMap<String, qdrant::Vector> = self
+                    .vectors
+                    .into_iter()
+                    .map(|(name, vector)| (name, vector.into()))
+                    .chain(vector.map(|vector| (String::new(), vector.into())))
+                    .collect();
+                named.extend(self.sparse_vectors.into_iter().map(|(name, sparse)| {
+                    let vector = qdrant::Vector {
+                        data: sparse.values,
+                        indices: Some(qdrant::SparseIndices {
+                            data: sparse.indices,
+                        }),
+                    };
+                    (name, vector)
+                }));
+                named.into()
+            }
+        };
+
         // Construct the `qdrant::PointStruct` and return it.
-        Ok(qdrant::PointStruct::new(
-            id,
-            self.vector.context("Vector is not set")?,
-            payload,
-        ))
+        Ok(qdrant::PointStruct::new(id, vectors, payload))
+    }
+}
+
+/// Converts a `qdrant::ScoredPoint` returned by a search back into a `ScoredNode`.
+///
+/// This is the inverse of the conversion above: the `path` and `content` fields of the payload
+/// become the path and chunk of the node, `source_range` its source range, `parent_id`,
+/// `chunk_index` and `sibling_count` its lineage, and all remaining fields except
+/// `last_updated_at` and `hash_version` become its metadata.
+impl TryFrom<qdrant::ScoredPoint> for ScoredNode {
+    type Error = anyhow::Error;
+
+    /// Converts the `qdrant::ScoredPoint` into a `ScoredNode`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the payload does not contain the `path` or `content` fields, or if the
+    /// `source_range` or lineage fields are invalid.
+    fn try_from(mut point: qdrant::ScoredPoint) -> Result<Self> {
+        let id = point.id.and_then(|id| match id.point_id_options {
+            Some(PointIdOptions::Num(id)) => Some(id),
+            _ => None,
+        });
+
+        let path = point
+            .payload
+            .remove("path")
+            .and_then(|path| path.as_str().cloned())
+            .context("Point has no path in payload")?;
+        let chunk = point
+            .payload
+            .remove("content")
+            .and_then(|content| content.as_str().cloned())
+            .context("Point has no content in payload")?;
+        let source_range = point
+            .payload
+            .remove("source_range")
+            .map(|range| serde_json::from_value(range.into()))
+            .transpose()
+            .context("Point has an invalid source range in payload")?;
+        let lineage = lineage_from_payload(&mut point.payload)?;
+        point.payload.remove("last_updated_at");
+        point.payload.remove("hash_version");
+
+        let metadata = point
+            .payload
+            .into_iter()
+            .map(|(k, v)| (k, v.into()))
+            .collect();
+
+        Ok(ScoredNode {
+            score: point.score,
+            node: IngestionNode {
+                id,
+                path: path.into(),
+                chunk,
+                metadata,
+                source_range,
+                lineage,
+                ..Default::default()
+            },
+        })
+    }
+}
+
+/// Removes the lineage fields from the payload, returning the lineage if they are all present.
+fn lineage_from_payload(payload: &mut HashMap<String, Value>) -> Result<Option<Lineage>> {
+    let parent_id = payload.remove("parent_id");
+    let chunk_index = payload.remove("chunk_index");
+    let sibling_count = payload.remove("sibling_count");
+
+    let (Some(parent_id), Some(chunk_index), Some(sibling_count)) =
+        (parent_id, chunk_index, sibling_count)
+    else {
+        return Ok(None);
+    };
+
+    let count = |value: Value, field: &str| {
+        serde_json::Value::from(value)
+            .as_u64()
+            .and_then(|value| usize::try_from(value).ok())
+            .with_context(|| format!("Point has an invalid {field} in payload"))
+    };
+
+    Ok(Some(Lineage {
+        parent_id: parent_id
+            .as_str()
+            .and_then(|id| id.parse().ok())
+            .context("Point has an invalid parent_id in payload")?,
+        chunk_index: count(chunk_index, "chunk_index")?,
+        sibling_count: count(sibling_count, "sibling_count")?,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingestion::{SourceRange, SparseVector};
+
+    #[test]
+    fn test_round_trip_through_scored_point() {
+        let node = IngestionNode {
+            path: "src/main.rs".into(),
+            chunk: "fn main() {}".into(),
+            vector: Some(vec![1.0]),
+            metadata: HashMap::from([
+                ("Questions and Answers".to_string(), "Q1".into()),
+                ("line".to_string(), 42.into()),
+                ("tags".to_string(), serde_json::json!(["rust", "main"])),
+            ]),
+            source_range: Some(SourceRange {
+                bytes: 0..12,
+                start_line: 1,
+                end_line: 1,
+            }),
+            lineage: Some(Lineage {
+                parent_id: u64::MAX,
+                chunk_index: 2,
+                sibling_count: 3,
+            }),
+            ..Default::default()
+        };
+
+        let point: qdrant::PointStruct = node.clone().try_into().unwrap();
+        let scored_point = qdrant::ScoredPoint {
+            id: point.id,
+            payload: point.payload,
+            score: 0.5,
+            ..Default::default()
+        };
+
+        let scored_node = ScoredNode::try_from(scored_point).unwrap();
+
+        assert_eq!(scored_node.score, 0.5);
+        assert_eq!(scored_node.node.id, Some(node.calculate_hash()));
+        assert_eq!(scored_node.node.path, node.path);
+        assert_eq!(scored_node.node.chunk, node.chunk);
+        assert_eq!(scored_node.node.metadata, node.metadata);
+        assert_eq!(scored_node.node.source_range, node.source_range);
+        assert_eq!(scored_node.node.lineage, node.lineage);
+    }
+
+    #[test]
+    fn test_rejects_metadata_colliding_with_reserved_keys() {
+        for key in RESERVED_PAYLOAD_KEYS {
+            let node = IngestionNode {
+                path: "src/main.rs".into(),
+                vector: Some(vec![1.0]),
+                metadata: HashMap::from([(key.to_string(), "user value".into())]),
+                ..Default::default()
+            };
+
+            let error = TryInto::<qdrant::PointStruct>::try_into(node).unwrap_err();
+            assert!(error.to_string().contains(key), "{error}");
+        }
+    }
+
+    #[test]
+    fn test_named_vectors() {
+        let node = IngestionNode {
+            vectors: HashMap::from([
+                ("chunk".to_string(), vec![1.0]),
+                ("metadata".to_string(), vec![0.5, 0.5]),
+            ]),
+            ..Default::default()
+        };
+
+        let point: qdrant::PointStruct = node.try_into().unwrap();
+        let Some(qdrant::vectors::VectorsOptions::Vectors(vectors)) =
+            point.vectors.and_then(|v| v.vectors_options)
+        else {
+            panic!("Expected named vectors");
+        };
+        assert_eq!(vectors.vectors["chunk"].data, vec![1.0]);
+        assert_eq!(vectors.vectors["metadata"].data, vec![0.5, 0.5]);
+    }
+
+    #[test]
+    fn test_rejects_unnamed_and_named_vectors_together() {
+        let node = IngestionNode {
+            vector: Some(vec![1.0]),
+            vectors: HashMap::from([("chunk".to_string(), vec![0.5])]),
+            ..Default::default()
+        };
+
+        let error = TryInto::<qdrant::PointStruct>::try_into(node).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Node has both an unnamed vector and named vectors"
+        );
+    }
+
+    #[test]
+    fn test_sparse_vectors_alongside_unnamed_vector() {
+        let node = IngestionNode {
+            vector: Some(vec![1.0]),
+            sparse_vectors: HashMap::from([(
+                "bm25".to_string(),
+                SparseVector {
+                    indices: vec![3, 7],
+                    values: vec![0.5, 1.5],
+                },
+            )]),
+            ..Default::default()
+        };
+
+        let point: qdrant::PointStruct = node.try_into().unwrap();
+        let Some(qdrant::vectors::VectorsOptions::Vectors(vectors)) =
+            point.vectors.and_then(|v| v.vectors_options)
+        else {
+            panic!("Expected named vectors");
+        };
+        assert_eq!(vectors.vectors[""].data, vec![1.0]);
+        assert_eq!(vectors.vectors["bm25"].data, vec![0.5, 1.5]);
+        assert_eq!(
+            vectors.vectors["bm25"].indices.as_ref().unwrap().data,
+            vec![3, 7]
+        );
     }
 }
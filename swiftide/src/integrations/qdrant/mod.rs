@@ -1,16 +1,21 @@
 //! This module provides integration with the Qdrant vector database.
 //! It includes functionalities to interact with Qdrant, such as creating and managing vector collections,
-//! storing data, and ensuring proper indexing for efficient searches.
+//! storing data, retrieving it by similarity, and ensuring proper indexing for efficient searches.
 
 mod ingestion_node;
 mod persist;
+mod retrieve;
+
+use std::collections::{HashMap, HashSet};
 
 use anyhow::Result;
 use derive_builder::Builder;
 use qdrant_client::client::QdrantClient;
 use qdrant_client::prelude::*;
 use qdrant_client::qdrant::vectors_config::Config;
-use qdrant_client::qdrant::{VectorParams, VectorsConfig};
+use qdrant_client::qdrant::{
+    SparseVectorConfig, SparseVectorParams, VectorParams, VectorParamsMap, VectorsConfig,
+};
 
 const DEFAULT_COLLECTION_NAME: &str = "swiftide";
 
@@ -18,21 +23,98 @@ const DEFAULT_COLLECTION_NAME: &str = "swiftide";
 ///
 /// This struct is used to interact with the Qdrant vector database, providing methods to create and manage
 /// vector collections, store data, and ensure proper indexing for efficient searches.
+///
+/// The collection either has a single unnamed vector of `vector_size`, stored from
+/// `IngestionNode::vector`, or the named vectors added with `with_vector`, stored from
+/// `IngestionNode::vectors`. Sparse vectors added with `with_sparse_vector` are stored from
+/// `IngestionNode::sparse_vectors`.
 #[derive(Builder)]
-#[builder(pattern = "owned")]
+#[builder(pattern = "owned", build_fn(validate = "Self::validate"))]
 pub struct Qdrant {
     /// The Qdrant client used to interact with the Qdrant vector database.
     client: QdrantClient,
     /// The name of the collection to be used in Qdrant. Defaults to "swiftide".
     #[builder(default = "DEFAULT_COLLECTION_NAME.to_string()")]
     collection_name: String,
-    /// The size of the vectors to be stored in the collection.
-    vector_size: usize,
+    /// The size of the unnamed vector to be stored in the collection.
+    #[builder(default, setter(strip_option))]
+    vector_size: Option<usize>,
+    /// The sizes of the named vectors to be stored in the collection, by name.
+    #[builder(default, setter(custom))]
+    vectors: HashMap<String, usize>,
+    /// The names of the sparse vectors to be stored in the collection.
+    #[builder(default, setter(custom))]
+    sparse_vectors: HashSet<String>,
+    /// The named vector to search with when retrieving. Required with more than one named vector,
+    /// defaults to the named vector if there is only one.
+    #[builder(default, setter(into, strip_option))]
+    search_vector_name: Option<String>,
     /// The batch size for operations. Optional.
     #[builder(default, setter(strip_option))]
     batch_size: Option<usize>,
 }
 
+impl QdrantBuilder {
+    /// Adds a named vector of the given size to the collection.
+    ///
+    /// Named vectors are stored from `IngestionNode::vectors`, i.e. to store a chunk-only and a
+    /// metadata embedding, or embeddings from two models side by side.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the vector, as used by the embedding transformer.
+    /// * `size` - The size of the vector.
+    pub fn with_vector(mut self, name: impl Into<String>, size: usize) -> Self {
+        self.vectors
+            .get_or_insert_with(HashMap::new)
+            .insert(name.into(), size);
+        self
+    }
+
+    /// Adds a named sparse vector to the collection, i.e. for hybrid search with `Bm25Embed`.
+    ///
+    /// Sparse vectors are stored from `IngestionNode::sparse_vectors`.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the sparse vector, as used by the transformer.
+    pub fn with_sparse_vector(mut self, name: impl Into<String>) -> Self {
+        self.sparse_vectors
+            .get_or_insert_with(HashSet::new)
+            .insert(name.into());
+        self
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let has_vector_size = matches!(self.vector_size, Some(Some(_)));
+        let has_vectors = self.vectors.as_ref().is_some_and(|v| !v.is_empty());
+        let has_sparse_vectors = self.sparse_vectors.as_ref().is_some_and(|v| !v.is_empty());
+
+        match (has_vector_size, has_vectors) {
+            (false, false) if !has_sparse_vectors => {
+                return Err(
+                    "Either a vector size, named vectors or sparse vectors are required".into(),
+                )
+            }
+            (true, true) => {
+                return Err("A vector size and named vectors cannot be used together".into())
+            }
+            _ => (),
+        }
+
+        let vectors = self.vectors.as_ref();
+        match self.search_vector_name.as_ref().and_then(Option::as_ref) {
+            Some(name) if !vectors.is_some_and(|vectors| vectors.contains_key(name)) => Err(
+                format!("The search vector name {name} is not one of the named vectors"),
+            ),
+            None if vectors.is_some_and(|vectors| vectors.len() > 1) => {
+                Err("A search vector name is required with more than one named vector".into())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
 impl Qdrant {
     /// Returns a new `QdrantBuilder` for constructing a `Qdrant` instance.
     pub fn builder() -> QdrantBuilder {
@@ -52,10 +134,18 @@ impl Qdrant {
         Ok(QdrantBuilder::default().client(QdrantClient::from_url(url.as_ref()).build()?))
     }
 
+    /// Returns the named vector to search with, if the collection has named vectors.
+    fn search_vector_name(&self) -> Option<String> {
+        self.search_vector_name.clone().or_else(|| {
+            // Validated to be the only named vector
+            self.vectors.keys().next().cloned()
+        })
+    }
+
     /// Creates an index in the Qdrant collection if it does not already exist.
     ///
     /// This method checks if the specified collection exists in Qdrant. If it does not exist, it creates a new collection
-    /// with the specified vector size and cosine distance metric.
+    /// with the specified vector size or named vectors, and cosine distance metric.
     ///
     /// # Returns
     ///
@@ -71,16 +161,126 @@ impl Qdrant {
         self.client
             .create_collection(&CreateCollection {
                 collection_name: self.collection_name.to_string(),
-                vectors_config: Some(VectorsConfig {
-                    config: Some(Config::Params(VectorParams {
-                        size: self.vector_size as u64,
-                        distance: Distance::Cosine.into(),
-                        ..Default::default()
-                    })),
-                }),
+                vectors_config: Some(self.vectors_config()),
+                sparse_vectors_config: self.sparse_vectors_config(),
                 ..Default::default()
             })
             .await?;
         Ok(())
     }
+
+    fn vectors_config(&self) -> VectorsConfig {
+        let params = |size: usize| VectorParams {
+            size: size as u64,
+            distance: Distance::Cosine.into(),
+            ..Default::default()
+        };
+
+        let config = match self.vector_size {
+            Some(size) => Config::Params(params(size)),
+            None => Config::ParamsMap(VectorParamsMap {
+                map: self
+                    .vectors
+                    .iter()
+                    .map(|(name, size)| (name.clone(), params(*size)))
+                    .collect(),
+            }),
+        };
+
+        VectorsConfig {
+            config: Some(config),
+        }
+    }
+
+    fn sparse_vectors_config(&self) -> Option<SparseVectorConfig> {
+        (!self.sparse_vectors.is_empty()).then(|| SparseVectorConfig {
+            map: self
+                .sparse_vectors
+                .iter()
+                .map(|name| (name.clone(), SparseVectorParams::default()))
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builder() -> QdrantBuilder {
+        Qdrant::try_from_url("http://localhost:6334").unwrap()
+    }
+
+    #[test]
+    fn test_named_vectors_config() {
+        let qdrant = builder()
+            .with_vector("chunk", 384)
+            .with_vector("metadata", 1536)
+            .search_vector_name("chunk")
+            .build()
+            .unwrap();
+
+        let Some(Config::ParamsMap(config)) = qdrant.vectors_config().config else {
+            panic!("Expected named vectors");
+        };
+        assert_eq!(config.map.len(), 2);
+        assert_eq!(config.map["chunk"].size, 384);
+        assert_eq!(config.map["metadata"].size, 1536);
+    }
+
+    #[test]
+    fn test_requires_exactly_one_vector_config() {
+        assert!(builder().build().is_err());
+        assert!(builder()
+            .vector_size(1536)
+            .with_vector("chunk", 384)
+            .build()
+            .is_err());
+        assert!(builder().vector_size(1536).build().is_ok());
+    }
+
+    #[test]
+    fn test_sparse_vectors_config() {
+        let qdrant = builder()
+            .vector_size(1536)
+            .with_sparse_vector("bm25")
+            .build()
+            .unwrap();
+
+        let config = qdrant.sparse_vectors_config().unwrap();
+        assert!(config.map.contains_key("bm25"));
+        assert!(builder().with_sparse_vector("bm25").build().is_ok());
+    }
+
+    #[test]
+    fn test_requires_search_vector_name_with_named_vectors() {
+        let named_vectors = || {
+            builder()
+                .with_vector("chunk", 384)
+                .with_vector("metadata", 1536)
+        };
+
+        assert!(named_vectors().build().is_err());
+        assert!(named_vectors().search_vector_name("other").build().is_err());
+        assert!(builder()
+            .vector_size(1536)
+            .search_vector_name("chunk")
+            .build()
+            .is_err());
+
+        let qdrant = named_vectors()
+            .search_vector_name("metadata")
+            .build()
+            .unwrap();
+        assert_eq!(qdrant.search_vector_name(), Some("metadata".to_string()));
+    }
+
+    #[test]
+    fn test_defaults_search_vector_name_to_single_named_vector() {
+        let qdrant = builder().with_vector("chunk", 384).build().unwrap();
+        assert_eq!(qdrant.search_vector_name(), Some("chunk".to_string()));
+
+        let qdrant = builder().vector_size(1536).build().unwrap();
+        assert_eq!(qdrant.search_vector_name(), None);
+    }
 }
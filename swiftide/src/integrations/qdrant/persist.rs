@@ -76,26 +76,23 @@ impl Persist for Qdrant {
     /// This function will return an error if any node conversion or storage operation fails.
     #[tracing::instrument(skip_all, name = "storage.qdrant.batch_store")]
     async fn batch_store(&self, nodes: Vec<crate::ingestion::IngestionNode>) -> IngestionStream {
-        let points = nodes
+        let points = match nodes
             .iter()
             .map(|node| node.clone().try_into())
-            .collect::<Result<Vec<_>>>();
-
-        if points.is_err() {
-            return stream::iter(vec![Err(points.unwrap_err())]).boxed();
-        }
-
-        let points = points.unwrap();
+            .collect::<Result<Vec<_>>>()
+        {
+            Ok(points) => points,
+            Err(e) => return stream::iter(vec![Err(e)]).boxed(),
+        };
 
         let result = self
             .client
             .upsert_points_blocking(self.collection_name.to_string(), None, points, None)
             .await;
 
-        if result.is_ok() {
-            stream::iter(nodes.into_iter().map(Ok)).boxed()
-        } else {
-            stream::iter(vec![Err(result.unwrap_err())]).boxed()
+        match result {
+            Ok(_) => stream::iter(nodes.into_iter().map(Ok)).boxed(),
+            Err(e) => stream::iter(vec![Err(e)]).boxed(),
         }
     }
 }
@@ -0,0 +1,100 @@
+//! This module provides an implementation of the `Retrieve` trait for the `Qdrant` struct.
+//! It searches the configured collection by vector similarity and converts the results back into
+//! scored nodes, allowing the Swiftide project to query data it stored in Qdrant.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use qdrant_client::qdrant::{self, SearchPoints, SparseIndices};
+
+use crate::{ingestion::SparseVector, query::ScoredNode, traits::Retrieve};
+
+use super::Qdrant;
+
+impl Qdrant {
+    /// Searches the Qdrant collection for the nodes most similar to the given sparse vector.
+    ///
+    /// # Parameters
+    ///
+    /// - `vector_name`: The name of the sparse vector to search, i.e. `bm25`.
+    /// - `vector`: The sparse query vector, i.e. computed with `Bm25Embed::sparse_embed`.
+    /// - `filter`: An optional Qdrant filter applied to the payload.
+    /// - `top_k`: The maximum number of nodes to return.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the search fails or if a point cannot be converted into a node.
+    #[tracing::instrument(skip_all, err, name = "storage.qdrant.retrieve_sparse")]
+    pub async fn retrieve_sparse(
+        &self,
+        vector_name: impl Into<String>,
+        vector: SparseVector,
+        filter: Option<qdrant::Filter>,
+        top_k: usize,
+    ) -> Result<Vec<ScoredNode>> {
+        self.search(SearchPoints {
+            collection_name: self.collection_name.to_string(),
+            vector: vector.values,
+            sparse_indices: Some(SparseIndices {
+                data: vector.indices,
+            }),
+            vector_name: Some(vector_name.into()),
+            filter,
+            limit: top_k as u64,
+            with_payload: Some(true.into()),
+            ..Default::default()
+        })
+        .await
+    }
+
+    async fn search(&self, search: SearchPoints) -> Result<Vec<ScoredNode>> {
+        let response = self.client.search_points(&search).await?;
+
+        response
+            .result
+            .into_iter()
+            .map(ScoredNode::try_from)
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Retrieve for Qdrant {
+    type Filter = qdrant::Filter;
+
+    /// Searches the Qdrant collection for the nodes most similar to the given vector.
+    ///
+    /// With named vectors, the vector named by `search_vector_name` is searched, or the only named
+    /// vector if it is not set.
+    ///
+    /// # Parameters
+    ///
+    /// - `vector`: The query vector, typically an embedded question.
+    /// - `filter`: An optional Qdrant filter applied to the payload.
+    /// - `top_k`: The maximum number of nodes to return.
+    ///
+    /// # Returns
+    ///
+    /// A `Result<Vec<ScoredNode>>` with the retrieved nodes ordered by score.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the search fails or if a point cannot be converted into a node.
+    #[tracing::instrument(skip_all, err, name = "storage.qdrant.retrieve")]
+    async fn retrieve(
+        &self,
+        vector: Vec<f32>,
+        filter: Option<Self::Filter>,
+        top_k: usize,
+    ) -> Result<Vec<ScoredNode>> {
+        self.search(SearchPoints {
+            collection_name: self.collection_name.to_string(),
+            vector,
+            vector_name: self.search_vector_name(),
+            filter,
+            limit: top_k as u64,
+            with_payload: Some(true.into()),
+            ..Default::default()
+        })
+        .await
+    }
+}
@@ -69,6 +69,8 @@ impl RedisNodeCache {
 
     /// Generates a Redis key for a given node using the key prefix and the node's hash.
     ///
+    /// The key includes the hash version, so entries written with an incompatible hash are ignored.
+    ///
     /// # Parameters
     ///
     /// * `node` - The node for which the key is to be generated.
@@ -77,7 +79,12 @@ impl RedisNodeCache {
     ///
     /// A `String` representing the Redis key for the node.
     fn key_for_node(&self, node: &IngestionNode) -> String {
-        format!("{}:{}", self.key_prefix, node.calculate_hash())
+        format!(
+            "{}:v{}:{}",
+            self.key_prefix,
+            IngestionNode::HASH_VERSION,
+            node.calculate_hash()
+        )
     }
 
     /// Resets the cache by deleting all keys with the specified prefix.
@@ -213,7 +220,11 @@ mod tests {
             path: "test".into(),
             chunk: "chunk".into(),
             vector: None,
+            vectors: HashMap::new(),
+            sparse_vectors: HashMap::new(),
             metadata: HashMap::new(),
+            source_range: None,
+            lineage: None,
         };
 
         let before_cache = cache.get(&node).await;
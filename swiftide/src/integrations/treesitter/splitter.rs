@@ -108,10 +108,11 @@ impl CodeSplitter {
     ///
     /// # Returns
     ///
-    /// * `Vec<String>` - A vector of code chunks as strings.
-    fn chunk_node(&self, node: Node, source: &str, mut last_end: usize) -> Vec<String> {
-        let mut new_chunks: Vec<String> = Vec::new();
-        let mut current_chunk = String::new();
+    /// * `Vec<Range<usize>>` - A vector of the byte ranges of the code chunks in the source.
+    fn chunk_node(&self, node: Node, source: &str, mut last_end: usize) -> Vec<Range<usize>> {
+        let mut new_chunks: Vec<Range<usize>> = Vec::new();
+        // The current chunk always spans from its start up to `last_end`
+        let mut current_chunk = last_end..last_end;
 
         for child in node.children(&mut node.walk()) {
             if child.end_byte() - child.start_byte() > self.max_bytes() {
@@ -119,15 +120,15 @@ impl CodeSplitter {
                 if !current_chunk.is_empty() && current_chunk.len() > self.min_bytes() {
                     new_chunks.push(current_chunk);
                 }
-                current_chunk = String::new();
+                current_chunk = child.end_byte()..child.end_byte();
                 new_chunks.extend(self.chunk_node(child, source, last_end));
             } else if current_chunk.len() + child.end_byte() - child.start_byte() > self.max_bytes()
             {
                 // Child would make the current chunk too big, so start a new chunk
-                new_chunks.push(current_chunk.trim().to_string());
-                current_chunk = source[last_end..child.end_byte()].to_string();
+                new_chunks.push(trim(source, current_chunk));
+                current_chunk = last_end..child.end_byte();
             } else {
-                current_chunk += &source[last_end..child.end_byte()];
+                current_chunk.end = child.end_byte();
             }
             last_end = child.end_byte();
         }
@@ -149,6 +150,24 @@ impl CodeSplitter {
     ///
     /// * `Result<Vec<String>>` - A result containing a vector of code chunks as strings, or an error if the code could not be parsed.
     pub fn split(&self, code: &str) -> Result<Vec<String>> {
+        Ok(self
+            .split_with_ranges(code)?
+            .into_iter()
+            .map(|(_, chunk)| chunk.to_string())
+            .collect())
+    }
+
+    /// Splits the given code into chunks based on the chunk size, together with the byte range
+    /// of each chunk in the code.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - The source code to be split.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<(Range<usize>, &str)>>` - A result containing the byte range and code of every chunk, or an error if the code could not be parsed.
+    pub fn split_with_ranges<'a>(&self, code: &'a str) -> Result<Vec<(Range<usize>, &'a str)>> {
         let mut parser = Parser::new();
         parser.set_language(&self.language.into())?;
         let tree = parser.parse(code, None).context("No nodes found")?;
@@ -157,7 +176,11 @@ impl CodeSplitter {
         if root_node.has_error() {
             anyhow::bail!("Root node has invalid syntax");
         } else {
-            Ok(self.chunk_node(root_node, code, 0))
+            Ok(self
+                .chunk_node(root_node, code, 0)
+                .into_iter()
+                .map(|range| (range.clone(), &code[range]))
+                .collect())
         }
     }
 
@@ -187,6 +210,13 @@ impl CodeSplitter {
     }
 }
 
+/// Shrinks the range to exclude leading and trailing whitespace.
+fn trim(source: &str, range: Range<usize>) -> Range<usize> {
+    let chunk = &source[range.clone()];
+    let start = range.start + chunk.len() - chunk.trim_start().len();
+    start..start + chunk.trim().len()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -253,6 +283,31 @@ mod test {
         )
     }
 
+    #[test]
+    fn test_split_with_ranges() {
+        let splitter = CodeSplitter::builder()
+            .try_language(SupportedLanguages::Rust)
+            .unwrap()
+            .chunk_size(50)
+            .build()
+            .unwrap();
+
+        let text = indoc! {r#"
+            fn main() {
+                println!("Hello, World!");
+                println!("Goodbye, World!");
+            }
+        "#};
+        let chunks = splitter.split_with_ranges(text).unwrap();
+
+        assert_eq!(chunks.len(), 3);
+        for (range, chunk) in &chunks {
+            assert_eq!(&text[range.clone()], *chunk);
+        }
+        assert_eq!(chunks[0].0, 0..9);
+        assert_eq!(chunks[1].0, 10..42);
+    }
+
     #[test]
     fn test_empty_text() {
         let splitter = CodeSplitter::builder()
@@ -1,7 +1,12 @@
+pub mod checkpoints;
 pub mod embeddings;
 pub mod ingestion;
 pub mod integrations;
 pub mod loaders;
+pub mod query;
+pub mod rate_limit;
+pub mod storage;
+pub mod tokenizer;
 pub mod traits;
 pub mod transformers;
 
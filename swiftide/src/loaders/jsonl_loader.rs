@@ -0,0 +1,95 @@
+use std::{
+    fs::File,
+    io::{BufRead as _, BufReader},
+    path::PathBuf,
+};
+
+use anyhow::Context as _;
+use futures_util::{stream, StreamExt};
+
+use crate::{ingestion::IngestionNode, ingestion::IngestionStream, Loader};
+
+/// The `JsonlLoader` struct loads nodes from a JSON Lines file, with one serialized `IngestionNode`
+/// per line.
+///
+/// Together with `JsonlStorage` it allows snapshotting the output of any pipeline stage and
+/// replaying it later, i.e. to re-embed chunks with a different model without generating their
+/// metadata again.
+pub struct JsonlLoader {
+    pub(crate) path: PathBuf,
+}
+
+impl JsonlLoader {
+    /// Creates a new `JsonlLoader` for the specified file.
+    ///
+    /// # Arguments
+    /// * `path` - The path to the JSON Lines file to load nodes from.
+    ///
+    /// # Returns
+    /// A new instance of `JsonlLoader`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl Loader for JsonlLoader {
+    /// Converts the `JsonlLoader` into a stream of `IngestionNode`.
+    ///
+    /// # Returns
+    /// An `IngestionStream` with a node for every non-empty line of the file.
+    ///
+    /// # Errors
+    /// The stream contains an error if the file cannot be opened, or for every line that cannot be
+    /// read or deserialized.
+    fn into_stream(self) -> IngestionStream {
+        let file = match File::open(&self.path)
+            .with_context(|| format!("Failed to open {}", self.path.display()))
+        {
+            Ok(file) => file,
+            Err(error) => return stream::iter(vec![Err(error)]).boxed(),
+        };
+
+        let nodes = BufReader::new(file)
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+            .map(move |(index, line)| {
+                let line = line?;
+                serde_json::from_str::<IngestionNode>(&line)
+                    .with_context(|| format!("Invalid node on line {}", index + 1))
+            });
+
+        stream::iter(nodes).boxed()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_loads_nodes_per_line() {
+        let dir = temp_dir::TempDir::new().unwrap();
+        let path = dir.child("nodes.jsonl");
+        std::fs::write(
+            &path,
+            "{\"path\":\"src/main.rs\",\"chunk\":\"fn main() {}\"}\n\nnot json\n",
+        )
+        .unwrap();
+
+        let results = JsonlLoader::new(&path)
+            .into_stream()
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(results.len(), 2);
+        let node = results[0].as_ref().unwrap();
+        assert_eq!(node.path, PathBuf::from("src/main.rs"));
+        assert_eq!(node.chunk, "fn main() {}");
+        assert!(results[1]
+            .as_ref()
+            .unwrap_err()
+            .to_string()
+            .contains("line 3"));
+    }
+}
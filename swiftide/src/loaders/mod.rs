@@ -1,9 +1,12 @@
 //! The `loaders` module provides functionality for loading files from a specified directory.
-//! It includes the `FileLoader` struct which is used to filter and stream files based on their extensions.
+//! It includes the `FileLoader` struct which is used to filter and stream files based on their extensions,
+//! and the `JsonlLoader` struct which replays nodes previously stored with `JsonlStorage`.
 //!
 //! This module is a part of the Swiftide project, designed for asynchronous file ingestion and processing.
-//! The `FileLoader` struct is re-exported for ease of use in other parts of the project.
+//! The `FileLoader` and `JsonlLoader` structs are re-exported for ease of use in other parts of the project.
 
 pub mod file_loader;
+pub mod jsonl_loader;
 
 pub use file_loader::FileLoader;
+pub use jsonl_loader::JsonlLoader;
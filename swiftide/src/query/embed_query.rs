@@ -0,0 +1,48 @@
+use std::sync::Arc;
+
+use crate::{query::Query, Embed, QueryTransformer};
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+
+/// `EmbedQuery` generates an embedding for a query, so it can be used for vector search.
+///
+/// Use the same `Embed` implementation and model that was used during ingestion, otherwise the
+/// query embedding will not be comparable with the stored vectors.
+#[derive(Debug)]
+pub struct EmbedQuery {
+    client: Arc<dyn Embed>,
+}
+
+impl EmbedQuery {
+    /// Creates a new instance of `EmbedQuery`.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - An implementation of the `Embed` trait used to embed the query.
+    pub fn new(client: impl Embed + 'static) -> Self {
+        Self {
+            client: Arc::new(client),
+        }
+    }
+}
+
+#[async_trait]
+impl QueryTransformer for EmbedQuery {
+    /// Embeds the current query and sets it as the query embedding.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the embedding fails or if no embedding is returned.
+    #[tracing::instrument(skip_all, name = "query.embed_query")]
+    async fn transform_query(&self, mut query: Query) -> Result<Query> {
+        let embedding = self
+            .client
+            .embed(vec![query.current.clone()])
+            .await?
+            .pop()
+            .context("No embedding returned for query")?;
+
+        query.embedding = Some(embedding);
+        Ok(query)
+    }
+}
@@ -0,0 +1,27 @@
+//! This module contains the query side of Swiftide, the counterpart of the ingestion pipeline.
+//!
+//! Where the ingestion pipeline loads, transforms and persists data, the query pipeline takes a
+//! question, transforms it (i.e. embeds it with the same `Embed` implementation used during
+//! ingestion), retrieves relevant documents and generates a response.
+//!
+//! The primary components include:
+//!
+//! - `Query`: The question being answered, together with its embedding, retrieved documents and
+//!   the generated response.
+//! - `QueryPipeline`: Runs a query through the configured query transformers, retriever and
+//!   response generator.
+//! - `EmbedQuery`, `VectorSearch` and `SimpleAnswer`: Generic stages built on the `Embed`, `Retrieve`
+//!   and `SimplePrompt` traits.
+
+mod embed_query;
+#[allow(clippy::module_inception)]
+mod query;
+mod query_pipeline;
+mod simple_answer;
+mod vector_search;
+
+pub use embed_query::EmbedQuery;
+pub use query::*;
+pub use query_pipeline::*;
+pub use simple_answer::SimpleAnswer;
+pub use vector_search::VectorSearch;
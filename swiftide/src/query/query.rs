@@ -0,0 +1,55 @@
+//! This module defines the `Query` struct, which carries a question through the query pipeline.
+//!
+//! Each stage of the pipeline takes a `Query` and returns it with more information attached:
+//! query transformers rewrite or embed it, retrievers add documents and response generators add
+//! the final response.
+
+use crate::ingestion::IngestionNode;
+
+/// Represents a question being answered by the query pipeline.
+#[derive(Debug, Default, Clone)]
+pub struct Query {
+    /// The question as it was originally asked.
+    pub original: String,
+    /// The question as it is currently used, after any query transformations.
+    pub current: String,
+    /// Optional vector representation of the current question.
+    pub embedding: Option<Vec<f32>>,
+    /// Documents retrieved for the question, ordered by relevance.
+    pub documents: Vec<ScoredNode>,
+    /// The generated response, if any.
+    pub response: Option<String>,
+}
+
+impl Query {
+    /// Creates a new `Query` from a question.
+    pub fn new(query: impl Into<String>) -> Self {
+        let original = query.into();
+        Self {
+            current: original.clone(),
+            original,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<&str> for Query {
+    fn from(query: &str) -> Self {
+        Query::new(query)
+    }
+}
+
+impl From<String> for Query {
+    fn from(query: String) -> Self {
+        Query::new(query)
+    }
+}
+
+/// An `IngestionNode` retrieved from a storage backend, together with its relevance score.
+#[derive(Debug, Default, Clone)]
+pub struct ScoredNode {
+    /// The similarity score reported by the storage backend. Higher is more relevant.
+    pub score: f32,
+    /// The retrieved node.
+    pub node: IngestionNode,
+}
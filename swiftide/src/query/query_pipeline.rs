@@ -0,0 +1,172 @@
+use crate::{QueryTransformer, ResponseGenerator, Retriever};
+use anyhow::Result;
+
+use std::sync::Arc;
+
+use super::Query;
+
+/// A pipeline for answering questions against data that was stored by an `IngestionPipeline`.
+///
+/// The `QueryPipeline` runs a query through the configured query transformers in order, retrieves
+/// documents with the retriever and finally generates a response with the response generator.
+///
+/// # Fields
+///
+/// * `query_transformers` - Transformers applied to the query before retrieval.
+/// * `retriever` - The retriever that adds relevant documents to the query.
+/// * `response_generator` - Optional generator that answers the query from its documents.
+#[derive(Default)]
+pub struct QueryPipeline {
+    query_transformers: Vec<Arc<dyn QueryTransformer>>,
+    retriever: Option<Arc<dyn Retriever>>,
+    response_generator: Option<Arc<dyn ResponseGenerator>>,
+}
+
+impl QueryPipeline {
+    /// Adds a query transformer to the pipeline.
+    ///
+    /// Query transformers run in the order they are added, before retrieval.
+    ///
+    /// # Arguments
+    ///
+    /// * `transformer` - A transformer that implements the `QueryTransformer` trait.
+    ///
+    /// # Returns
+    ///
+    /// An instance of `QueryPipeline` with the transformer added.
+    pub fn then_transform_query(mut self, transformer: impl QueryTransformer + 'static) -> Self {
+        self.query_transformers.push(Arc::new(transformer));
+        self
+    }
+
+    /// Sets the retriever of the pipeline.
+    ///
+    /// # Arguments
+    ///
+    /// * `retriever` - A retriever that implements the `Retriever` trait.
+    ///
+    /// # Returns
+    ///
+    /// An instance of `QueryPipeline` with the configured retriever.
+    pub fn then_retrieve(mut self, retriever: impl Retriever + 'static) -> Self {
+        self.retriever = Some(Arc::new(retriever));
+        self
+    }
+
+    /// Sets the response generator of the pipeline.
+    ///
+    /// # Arguments
+    ///
+    /// * `generator` - A response generator that implements the `ResponseGenerator` trait.
+    ///
+    /// # Returns
+    ///
+    /// An instance of `QueryPipeline` with the configured response generator.
+    pub fn then_generate_response(mut self, generator: impl ResponseGenerator + 'static) -> Self {
+        self.response_generator = Some(Arc::new(generator));
+        self
+    }
+
+    /// Runs a query through the pipeline.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The question to answer, anything that converts into a `Query`.
+    ///
+    /// # Returns
+    ///
+    /// The `Query` with its retrieved documents and, if a response generator is configured, the response.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no retriever is configured or if any stage of the pipeline fails.
+    #[tracing::instrument(skip_all, fields(documents), name = "query_pipeline.query")]
+    pub async fn query(&self, query: impl Into<Query>) -> Result<Query> {
+        let Some(retriever) = &self.retriever else {
+            anyhow::bail!("No retriever configured for query pipeline");
+        };
+
+        let mut query = query.into();
+        tracing::debug!(query = query.original, "Starting query pipeline");
+
+        for transformer in &self.query_transformers {
+            query = transformer.transform_query(query).await?;
+        }
+
+        query = retriever.retrieve(query).await?;
+        tracing::Span::current().record("documents", query.documents.len());
+
+        if let Some(generator) = &self.response_generator {
+            query = generator.generate_response(query).await?;
+        }
+
+        Ok(query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::ScoredNode;
+    use crate::traits::*;
+    use mockall::Sequence;
+
+    /// Tests a simple run of the query pipeline.
+    #[test_log::test(tokio::test)]
+    async fn test_simple_query() {
+        let mut transformer = MockQueryTransformer::new();
+        let mut retriever = MockRetriever::new();
+        let mut generator = MockResponseGenerator::new();
+
+        let mut seq = Sequence::new();
+
+        transformer
+            .expect_transform_query()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|mut query| {
+                query.embedding = Some(vec![1.0]);
+                Ok(query)
+            });
+
+        retriever
+            .expect_retrieve()
+            .times(1)
+            .in_sequence(&mut seq)
+            .withf(|query| query.embedding.is_some())
+            .returning(|mut query| {
+                query.documents.push(ScoredNode {
+                    score: 1.0,
+                    ..Default::default()
+                });
+                Ok(query)
+            });
+
+        generator
+            .expect_generate_response()
+            .times(1)
+            .in_sequence(&mut seq)
+            .withf(|query| query.documents.len() == 1)
+            .returning(|mut query| {
+                query.response = Some("answer".to_string());
+                Ok(query)
+            });
+
+        let pipeline = QueryPipeline::default()
+            .then_transform_query(transformer)
+            .then_retrieve(retriever)
+            .then_generate_response(generator);
+
+        let query = pipeline.query("question").await.unwrap();
+
+        assert_eq!(query.original, "question");
+        assert_eq!(query.response.as_deref(), Some("answer"));
+    }
+
+    #[tokio::test]
+    async fn test_query_without_retriever() {
+        let pipeline = QueryPipeline::default();
+
+        assert!(pipeline.query("question").await.is_err());
+    }
+}
@@ -0,0 +1,133 @@
+use std::sync::Arc;
+
+use crate::{query::Query, ResponseGenerator, SimplePrompt};
+use anyhow::Result;
+use async_trait::async_trait;
+use indoc::indoc;
+
+/// `SimpleAnswer` answers a query by prompting an LLM with the question and the retrieved documents.
+#[derive(Debug)]
+pub struct SimpleAnswer {
+    client: Arc<dyn SimplePrompt>,
+    prompt: String,
+}
+
+impl SimpleAnswer {
+    /// Creates a new instance of `SimpleAnswer`.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - An implementation of the `SimplePrompt` trait used to generate the answer.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of `SimpleAnswer` with a default prompt.
+    pub fn new(client: impl SimplePrompt + 'static) -> Self {
+        Self {
+            client: Arc::new(client),
+            prompt: default_prompt(),
+        }
+    }
+
+    /// Overrides the default prompt template.
+    ///
+    /// The template can use the `{question}` and `{context}` placeholders.
+    pub fn with_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.prompt = prompt.into();
+        self
+    }
+}
+
+/// Returns the default prompt template for answering a question.
+///
+/// This template includes placeholders for the question and the retrieved context.
+///
+/// # Returns
+///
+/// A string representing the default prompt template.
+fn default_prompt() -> String {
+    indoc! {r#"
+
+            # Task
+            Answer the following question using only the provided context.
+
+            # Constraints
+            * If the context does not contain the answer, say that you do not know.
+            * Do not make up an answer.
+
+            # Question
+            {question}
+
+            # Context
+            {context}
+
+        "#}
+    .to_string()
+}
+
+/// Fills the `{question}` and `{context}` placeholders in a single pass, so placeholders in the
+/// question or the documents are kept as is.
+fn render(template: &str, question: &str, context: &str) -> String {
+    let mut output = String::with_capacity(template.len() + question.len() + context.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        if let Some(after) = rest.strip_prefix("{question}") {
+            output.push_str(question);
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("{context}") {
+            output.push_str(context);
+            rest = after;
+        } else {
+            output.push('{');
+            rest = &rest[1..];
+        }
+    }
+    output.push_str(rest);
+
+    output
+}
+
+#[async_trait]
+impl ResponseGenerator for SimpleAnswer {
+    /// Generates a response for the query from its retrieved documents.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the `SimplePrompt` client fails to generate a response.
+    #[tracing::instrument(skip_all, name = "query.simple_answer")]
+    async fn generate_response(&self, mut query: Query) -> Result<Query> {
+        let context = query
+            .documents
+            .iter()
+            .map(|document| document.node.chunk.as_str())
+            .collect::<Vec<_>>()
+            .join("\n---\n");
+
+        let prompt = render(&self.prompt, &query.original, &context);
+
+        query.response = Some(self.client.prompt(&prompt).await?);
+
+        Ok(query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_keeps_placeholders_in_values() {
+        assert_eq!(
+            render(
+                "Q: {question}\nC: {context} {other}",
+                "What is {context}?",
+                "Docs about {question}"
+            ),
+            "Q: What is {context}?\nC: Docs about {question} {other}"
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use std::fmt::Debug;
+
+use crate::{query::Query, Retrieve, Retriever};
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+
+const DEFAULT_TOP_K: usize = 10;
+
+/// `VectorSearch` retrieves documents for a query by searching a `Retrieve` backend with the query embedding.
+///
+/// The query must be embedded before retrieval, i.e. with `EmbedQuery`.
+pub struct VectorSearch<R: Retrieve> {
+    backend: R,
+    filter: Option<R::Filter>,
+    top_k: usize,
+}
+
+impl<R: Retrieve> VectorSearch<R> {
+    /// Creates a new instance of `VectorSearch` returning the 10 most similar documents.
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - A storage backend that implements the `Retrieve` trait.
+    pub fn new(backend: R) -> Self {
+        Self {
+            backend,
+            filter: None,
+            top_k: DEFAULT_TOP_K,
+        }
+    }
+
+    /// Sets the maximum number of documents to retrieve.
+    pub fn with_top_k(mut self, top_k: usize) -> Self {
+        self.top_k = top_k;
+        self
+    }
+
+    /// Sets a backend specific filter applied to every search.
+    pub fn with_filter(mut self, filter: R::Filter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+}
+
+impl<R: Retrieve> Debug for VectorSearch<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VectorSearch")
+            .field("top_k", &self.top_k)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl<R: Retrieve> Retriever for VectorSearch<R> {
+    /// Searches the backend with the query embedding and adds the results as documents.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query has not been embedded or if the search fails.
+    #[tracing::instrument(skip_all, name = "query.vector_search")]
+    async fn retrieve(&self, mut query: Query) -> Result<Query> {
+        let embedding = query
+            .embedding
+            .clone()
+            .context("Query has no embedding, embed it before retrieval")?;
+
+        query.documents = self
+            .backend
+            .retrieve(embedding, self.filter.clone(), self.top_k)
+            .await?;
+
+        Ok(query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::ScoredNode;
+    use crate::traits::MockRetrieve;
+
+    #[tokio::test]
+    async fn test_retrieves_with_embedding() {
+        let mut backend = MockRetrieve::new();
+        backend
+            .expect_retrieve()
+            .withf(|vector, _, top_k| vector == &vec![1.0] && *top_k == 5)
+            .returning(|_, _, _| Ok(vec![ScoredNode::default()]));
+
+        let query = Query {
+            embedding: Some(vec![1.0]),
+            ..Query::new("question")
+        };
+
+        let query = VectorSearch::new(backend)
+            .with_top_k(5)
+            .retrieve(query)
+            .await
+            .unwrap();
+
+        assert_eq!(query.documents.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_requires_embedding() {
+        let backend = MockRetrieve::new();
+
+        let result = VectorSearch::new(backend)
+            .retrieve(Query::new("question"))
+            .await;
+
+        assert!(result.is_err());
+    }
+}
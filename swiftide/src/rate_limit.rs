@@ -0,0 +1,223 @@
+//! This module provides client-side rate limiting for stages that call external services.
+//!
+//! Providers enforce quotas in requests per minute and tokens per minute, which do not map to the
+//! concurrency of a stage. A `RateLimiter` enforces these quotas directly. It is cheap to clone and
+//! all clones share the same quota, so a single limiter can be shared by every stage that uses the
+//! same account.
+//!
+//! Any `SimplePrompt` or `Embed` implementation can be limited by wrapping it in `RateLimited`. The
+//! `OpenAI` integration also accepts a limiter directly, which is shared by all clones of the client.
+//!
+//! Tokens are estimated from the input at roughly four characters per token. Completion tokens are
+//! not known before the request and are not counted.
+
+use std::{
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::{tokenizer::EstimatedTokenizer, Embed, Embeddings, SimplePrompt, Tokenizer};
+
+/// Limits the requests and tokens per minute sent to a service.
+///
+/// Quotas refill continuously, so after a burst up to the full quota, requests are spread out
+/// evenly. Requests that exceed the quota wait until enough of it has refilled.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimiter {
+    requests: Option<Arc<Bucket>>,
+    tokens: Option<Arc<Bucket>>,
+}
+
+impl RateLimiter {
+    /// Creates a new `RateLimiter` without any limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits the number of requests per minute.
+    pub fn with_requests_per_minute(mut self, requests_per_minute: u32) -> Self {
+        self.requests = Some(Arc::new(Bucket::per_minute(requests_per_minute)));
+        self
+    }
+
+    /// Limits the estimated number of input tokens per minute.
+    pub fn with_tokens_per_minute(mut self, tokens_per_minute: u32) -> Self {
+        self.tokens = Some(Arc::new(Bucket::per_minute(tokens_per_minute)));
+        self
+    }
+
+    /// Waits until a request with the given number of tokens fits within the limits.
+    pub async fn acquire(&self, tokens: usize) {
+        let wait = [(&self.requests, 1), (&self.tokens, tokens)]
+            .into_iter()
+            .filter_map(|(bucket, amount)| bucket.as_ref().map(|bucket| bucket.reserve(amount)))
+            .max()
+            .unwrap_or_default();
+
+        if !wait.is_zero() {
+            tracing::debug!(?wait, "Rate limited, waiting");
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Waits until a request with the given input fits within the limits.
+    pub async fn acquire_for(&self, input: &[impl AsRef<str>]) {
+        self.acquire(
+            input
+                .iter()
+                .map(|text| estimate_tokens(text.as_ref()))
+                .sum(),
+        )
+        .await;
+    }
+}
+
+/// A quota that refills continuously up to its capacity.
+#[derive(Debug)]
+struct Bucket {
+    capacity: f64,
+    per_second: f64,
+    state: Mutex<BucketState>,
+}
+
+#[derive(Debug)]
+struct BucketState {
+    available: f64,
+    updated_at: Instant,
+}
+
+impl Bucket {
+    fn per_minute(limit: u32) -> Self {
+        let capacity = f64::from(limit.max(1));
+        Self {
+            capacity,
+            per_second: capacity / 60.0,
+            state: Mutex::new(BucketState {
+                available: capacity,
+                updated_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Takes the amount from the quota and returns how long to wait until it is available.
+    ///
+    /// The amount is reserved immediately, even if the quota goes negative, so concurrent callers
+    /// queue up behind each other instead of all retrying at the same time.
+    fn reserve(&self, amount: usize) -> Duration {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let now = Instant::now();
+        let refilled = now.duration_since(state.updated_at).as_secs_f64() * self.per_second;
+        state.available = (state.available + refilled).min(self.capacity);
+        state.updated_at = now;
+
+        #[allow(clippy::cast_precision_loss)]
+        let amount = amount as f64;
+        state.available -= amount;
+
+        if state.available >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-state.available / self.per_second)
+        }
+    }
+}
+
+/// Estimates the number of tokens in a text, at roughly four characters per token.
+fn estimate_tokens(text: &str) -> usize {
+    EstimatedTokenizer::new().count_tokens(text)
+}
+
+/// Wraps a `SimplePrompt` or `Embed` implementation and limits its requests with a `RateLimiter`.
+///
+/// # Example
+///
+/// ```ignore
+/// let limiter = RateLimiter::new().with_requests_per_minute(500).with_tokens_per_minute(200_000);
+///
+/// IngestionPipeline::from_loader(loader)
+///     .then(MetadataQACode::new(RateLimited::new(openai_client.clone(), limiter.clone())))
+///     .then(MetadataQAText::new(RateLimited::new(openai_client.clone(), limiter)))
+/// ```
+#[derive(Debug, Clone)]
+pub struct RateLimited<T> {
+    inner: T,
+    limiter: RateLimiter,
+}
+
+impl<T> RateLimited<T> {
+    /// Wraps a client in a rate limiter.
+    ///
+    /// # Arguments
+    ///
+    /// * `inner` - The `SimplePrompt` or `Embed` implementation to limit.
+    /// * `limiter` - The `RateLimiter` to limit it with, possibly shared with other clients.
+    pub fn new(inner: T, limiter: RateLimiter) -> Self {
+        Self { inner, limiter }
+    }
+}
+
+#[async_trait]
+impl<T: SimplePrompt> SimplePrompt for RateLimited<T> {
+    async fn prompt(&self, prompt: &str) -> Result<String> {
+        self.limiter.acquire_for(&[prompt]).await;
+        self.inner.prompt(prompt).await
+    }
+}
+
+#[async_trait]
+impl<T: Embed> Embed for RateLimited<T> {
+    async fn embed(&self, input: Vec<String>) -> Result<Embeddings> {
+        self.limiter.acquire_for(&input).await;
+        self.inner.embed(input).await
+    }
+
+    fn max_tokens(&self) -> Option<usize> {
+        self.inner.max_tokens()
+    }
+
+    fn tokenizer(&self) -> Option<Arc<dyn Tokenizer>> {
+        self.inner.tokenizer()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_allows_burst_up_to_limit() {
+        let limiter = RateLimiter::new().with_requests_per_minute(60);
+        let start = Instant::now();
+
+        for _ in 0..60 {
+            limiter.acquire(0).await;
+        }
+
+        assert!(start.elapsed() < Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_waits_when_exceeding_limit() {
+        let bucket = Bucket::per_minute(60);
+
+        assert_eq!(bucket.reserve(60), Duration::ZERO);
+        let wait = bucket.reserve(2);
+
+        // Refills at one per second, so two more take about two seconds
+        assert!(wait > Duration::from_millis(1900) && wait <= Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn test_clones_share_limits() {
+        let limiter = RateLimiter::new().with_tokens_per_minute(100);
+        let clone = limiter.clone();
+
+        clone.acquire_for(&["a".repeat(400)]).await;
+
+        assert!(limiter.tokens.as_ref().unwrap().reserve(1) > Duration::ZERO);
+    }
+}
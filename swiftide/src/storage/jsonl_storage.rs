@@ -0,0 +1,172 @@
+use std::path::PathBuf;
+
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use futures_util::{stream, StreamExt};
+use tokio::{fs::File, io::AsyncWriteExt as _, sync::Mutex};
+
+use crate::{
+    ingestion::{IngestionNode, IngestionStream},
+    Persist,
+};
+
+/// `JsonlStorage` writes nodes to a JSON Lines file, with one serialized `IngestionNode` per line.
+///
+/// It can be added after any stage to snapshot its output, which can then be replayed with
+/// `JsonlLoader`. By default the file is truncated when the pipeline starts.
+///
+/// # Example
+///
+/// ```ignore
+/// IngestionPipeline::from_loader(FileLoader::new(".").with_extensions(&["rs"]))
+///     .then_chunk(ChunkCode::try_for_language("rust")?)
+///     .then(MetadataQACode::new(openai_client.clone()))
+///     .then_store_with(JsonlStorage::new("chunks.jsonl"))
+///     .run()
+///     .await?;
+/// ```
+#[derive(Debug)]
+pub struct JsonlStorage {
+    path: PathBuf,
+    append: bool,
+    batch_size: Option<usize>,
+    file: Mutex<Option<File>>,
+}
+
+impl JsonlStorage {
+    /// Creates a new `JsonlStorage` writing to the specified file.
+    ///
+    /// # Arguments
+    /// * `path` - The path to the JSON Lines file. Missing parent directories are created.
+    ///
+    /// # Returns
+    /// A new instance of `JsonlStorage`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            append: false,
+            batch_size: None,
+            file: Mutex::new(None),
+        }
+    }
+
+    /// Appends to an existing file instead of truncating it.
+    pub fn with_append(mut self, append: bool) -> Self {
+        self.append = append;
+        self
+    }
+
+    /// Writes nodes in batches of the given size.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = Some(batch_size);
+        self
+    }
+
+    /// Serializes the nodes and writes them to the file, opening it if needed.
+    async fn write(&self, nodes: &[IngestionNode]) -> Result<()> {
+        let mut lines = Vec::new();
+        for node in nodes {
+            serde_json::to_writer(&mut lines, node)?;
+            lines.push(b'\n');
+        }
+
+        let mut file = self.file.lock().await;
+        if file.is_none() {
+            *file = Some(self.open(true).await?);
+        }
+        let file = file.as_mut().context("File is not open")?;
+
+        file.write_all(&lines).await?;
+        file.flush().await?;
+        Ok(())
+    }
+
+    async fn open(&self, append: bool) -> Result<File> {
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        tokio::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(&self.path)
+            .await
+            .with_context(|| format!("Failed to open {}", self.path.display()))
+    }
+}
+
+#[async_trait]
+impl Persist for JsonlStorage {
+    /// Opens the file, truncating it unless `with_append` is set.
+    #[tracing::instrument(skip_all, err)]
+    async fn setup(&self) -> Result<()> {
+        let file = self.open(self.append).await?;
+        *self.file.lock().await = Some(file);
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all, err, name = "storage.jsonl.store")]
+    async fn store(&self, node: IngestionNode) -> Result<IngestionNode> {
+        self.write(std::slice::from_ref(&node)).await?;
+        Ok(node)
+    }
+
+    #[tracing::instrument(skip_all, name = "storage.jsonl.batch_store")]
+    async fn batch_store(&self, nodes: Vec<IngestionNode>) -> IngestionStream {
+        match self.write(&nodes).await {
+            Ok(()) => stream::iter(nodes.into_iter().map(Ok)).boxed(),
+            Err(error) => stream::iter(vec![Err(error)]).boxed(),
+        }
+    }
+
+    fn batch_size(&self) -> Option<usize> {
+        self.batch_size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{loaders::JsonlLoader, Loader};
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn test_round_trip_through_loader() {
+        let dir = temp_dir::TempDir::new().unwrap();
+        let path = dir.path().join("snapshots").join("nodes.jsonl");
+        let node = IngestionNode {
+            path: "src/main.rs".into(),
+            chunk: "fn main() {}".to_string(),
+            vector: Some(vec![0.5, 1.0]),
+            metadata: HashMap::from([("line".to_string(), 1.into())]),
+            ..Default::default()
+        };
+
+        let storage = JsonlStorage::new(&path);
+        storage.setup().await.unwrap();
+        storage.store(node.clone()).await.unwrap();
+        let stored = storage
+            .batch_store(vec![node.clone()])
+            .await
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(stored.len(), 1);
+
+        let loaded = JsonlLoader::new(&path)
+            .into_stream()
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(loaded.len(), 2);
+        let loaded = loaded[1].as_ref().unwrap();
+        assert_eq!(loaded.path, node.path);
+        assert_eq!(loaded.chunk, node.chunk);
+        assert_eq!(loaded.vector, node.vector);
+        assert_eq!(loaded.metadata, node.metadata);
+
+        // Setting up again truncates the file
+        storage.setup().await.unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+    }
+}
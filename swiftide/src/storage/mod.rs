@@ -0,0 +1,8 @@
+//! The `storage` module provides storage backends that do not depend on external services.
+//!
+//! It includes the `JsonlStorage` struct, which writes nodes to a JSON Lines file so the output of a
+//! pipeline can be inspected or replayed later with `loaders::JsonlLoader`.
+
+pub mod jsonl_storage;
+
+pub use jsonl_storage::JsonlStorage;
@@ -0,0 +1,64 @@
+//! This module provides tokenizers to count and truncate text in tokens.
+//!
+//! Models limit their input in tokens, so embedding transformers use a `Tokenizer` to keep inputs
+//! within these limits. Any tokenizer can be used by implementing the `Tokenizer` trait, i.e. one
+//! wrapping the exact tokenizer of a model.
+
+use crate::Tokenizer;
+
+/// Estimates tokens from the number of characters, at roughly four characters per token.
+///
+/// This is close for English text and code with the tokenizers of most models, but not exact. Use
+/// a lower limit or the exact tokenizer of the model when inputs must never exceed it.
+#[derive(Debug, Clone, Copy)]
+pub struct EstimatedTokenizer {
+    chars_per_token: usize,
+}
+
+impl Default for EstimatedTokenizer {
+    fn default() -> Self {
+        Self { chars_per_token: 4 }
+    }
+}
+
+impl EstimatedTokenizer {
+    /// Creates a new `EstimatedTokenizer` with four characters per token.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of characters per token.
+    pub fn with_chars_per_token(mut self, chars_per_token: usize) -> Self {
+        self.chars_per_token = chars_per_token.max(1);
+        self
+    }
+}
+
+impl Tokenizer for EstimatedTokenizer {
+    fn count_tokens(&self, text: &str) -> usize {
+        text.chars().count().div_ceil(self.chars_per_token)
+    }
+
+    fn truncate<'a>(&self, text: &'a str, max_tokens: usize) -> &'a str {
+        let max_chars = max_tokens.saturating_mul(self.chars_per_token);
+        match text.char_indices().nth(max_chars) {
+            Some((end, _)) => &text[..end],
+            None => text,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_and_truncates_on_char_boundaries() {
+        let tokenizer = EstimatedTokenizer::new();
+
+        assert_eq!(tokenizer.count_tokens("héllo wörld"), 3);
+        assert_eq!(tokenizer.truncate("héllo wörld", 2), "héllo wö");
+        assert_eq!(tokenizer.truncate("héllo", 2), "héllo");
+        assert_eq!(tokenizer.count_tokens(""), 0);
+    }
+}
@@ -1,6 +1,11 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, sync::Arc};
 
-use crate::{ingestion::IngestionNode, ingestion::IngestionStream, Embeddings};
+use crate::{
+    ingestion::IngestionNode,
+    ingestion::IngestionStream,
+    query::{Query, ScoredNode},
+    Embeddings,
+};
 use anyhow::Result;
 use async_trait::async_trait;
 
@@ -67,9 +72,37 @@ pub trait NodeCache: Send + Sync + Debug {
     async fn set(&self, node: &IngestionNode);
 }
 
+#[cfg_attr(test, automock)]
+#[async_trait]
+/// Records nodes that made it through the whole pipeline, so a restarted run can skip them
+pub trait Checkpoint: Send + Sync + Debug {
+    async fn is_completed(&self, node: &IngestionNode) -> Result<bool>;
+    async fn mark_completed(&self, node: &IngestionNode) -> Result<()>;
+}
+
+#[cfg_attr(test, automock)]
 #[async_trait]
 pub trait Embed: Debug + Send + Sync {
     async fn embed(&self, input: Vec<String>) -> Result<Embeddings>;
+
+    /// The maximum number of tokens of a single input, if the model has a known limit
+    fn max_tokens(&self) -> Option<usize> {
+        None
+    }
+
+    /// The exact tokenizer of the model, if known, to count and truncate inputs with
+    fn tokenizer(&self) -> Option<Arc<dyn Tokenizer>> {
+        None
+    }
+}
+
+/// Counts and truncates text in tokens, i.e. to fit inputs within the limits of a model
+pub trait Tokenizer: Debug + Send + Sync {
+    fn count_tokens(&self, text: &str) -> usize;
+
+    /// Returns the longest prefix of the text with at most `max_tokens` tokens. It must not be
+    /// empty if the text is not empty and `max_tokens` is at least 1.
+    fn truncate<'a>(&self, text: &'a str, max_tokens: usize) -> &'a str;
 }
 
 #[async_trait]
@@ -89,3 +122,40 @@ pub trait Persist: Send + Sync {
         None
     }
 }
+
+#[cfg_attr(test, automock(type Filter = ();))]
+#[async_trait]
+/// Retrieves persisted nodes by vector similarity
+pub trait Retrieve: Send + Sync {
+    /// Backend specific filter to narrow down the search
+    type Filter: Clone + Send + Sync;
+
+    /// Returns the `top_k` most similar nodes to the query vector, ordered by score
+    async fn retrieve(
+        &self,
+        vector: Vec<f32>,
+        filter: Option<Self::Filter>,
+        top_k: usize,
+    ) -> Result<Vec<ScoredNode>>;
+}
+
+#[cfg_attr(test, automock)]
+#[async_trait]
+/// Transforms a query before retrieval, i.e. rewriting or embedding it
+pub trait QueryTransformer: Send + Sync + Debug {
+    async fn transform_query(&self, query: Query) -> Result<Query>;
+}
+
+#[cfg_attr(test, automock)]
+#[async_trait]
+/// Retrieves documents relevant to a query and adds them to it
+pub trait Retriever: Send + Sync + Debug {
+    async fn retrieve(&self, query: Query) -> Result<Query>;
+}
+
+#[cfg_attr(test, automock)]
+#[async_trait]
+/// Generates a response for a query from its retrieved documents
+pub trait ResponseGenerator: Send + Sync + Debug {
+    async fn generate_response(&self, query: Query) -> Result<Query>;
+}
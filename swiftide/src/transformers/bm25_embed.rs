@@ -0,0 +1,233 @@
+//! This module defines the `Bm25Embed` transformer, which computes sparse term-weight vectors
+//! locally, without any external service.
+//!
+//! Sparse vectors complement dense embeddings in hybrid search: they match exact terms, such as
+//! identifiers in code, which dense embeddings often miss.
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::{
+    ingestion::{EmbedFormat, IngestionNode, SparseVector, StableHasher},
+    Transformer,
+};
+
+const DEFAULT_VECTOR_NAME: &str = "bm25";
+
+/// `Bm25Embed` computes a BM25-style sparse vector of the terms in a node and stores it in
+/// `IngestionNode::sparse_vectors`.
+///
+/// The text is split into terms on everything but letters, digits and underscores. Identifiers
+/// such as `get_user` or `getUser` are included as a whole and split into their parts, so both
+/// exact identifiers and the words in them match. Terms are lowercased and hashed into stable
+/// indices.
+///
+/// Each term is weighted by its BM25 term frequency, saturated with `k1` and normalized by the
+/// length of the text with `b`. The inverse document frequency requires statistics of the whole
+/// collection and is not applied.
+///
+/// By default only the chunk is used, see `with_embed_format`. Queries should be embedded with
+/// `sparse_embed` of the same configuration.
+#[derive(Debug, Clone)]
+pub struct Bm25Embed {
+    vector_name: String,
+    k1: f32,
+    b: f32,
+    average_length: f32,
+    embed_format: EmbedFormat,
+    concurrency: Option<usize>,
+}
+
+impl Default for Bm25Embed {
+    fn default() -> Self {
+        Self {
+            vector_name: DEFAULT_VECTOR_NAME.to_string(),
+            k1: 1.2,
+            b: 0.75,
+            average_length: 256.0,
+            embed_format: EmbedFormat::chunk_only(),
+            concurrency: None,
+        }
+    }
+}
+
+impl Bm25Embed {
+    /// Creates a new `Bm25Embed` storing the sparse vector under the name `bm25`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the name of the sparse vector. Defaults to `bm25`.
+    pub fn with_vector_name(mut self, vector_name: impl Into<String>) -> Self {
+        self.vector_name = vector_name.into();
+        self
+    }
+
+    /// Sets the term frequency saturation. Defaults to 1.2.
+    pub fn with_k1(mut self, k1: f32) -> Self {
+        self.k1 = k1;
+        self
+    }
+
+    /// Sets how strongly term frequencies are normalized by the length of the text, from 0 to 1.
+    /// Defaults to 0.75.
+    pub fn with_b(mut self, b: f32) -> Self {
+        self.b = b;
+        self
+    }
+
+    /// Sets the average length of a text in terms, used for length normalization. Defaults to 256.
+    pub fn with_average_length(mut self, average_length: f32) -> Self {
+        self.average_length = average_length;
+        self
+    }
+
+    /// Sets the format of the text that is embedded. Defaults to `EmbedFormat::chunk_only()`.
+    pub fn with_embed_format(mut self, embed_format: EmbedFormat) -> Self {
+        self.embed_format = embed_format;
+        self
+    }
+
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = Some(concurrency);
+        self
+    }
+
+    /// Computes the sparse vector of a text, i.e. of a query.
+    pub fn sparse_embed(&self, text: &str) -> SparseVector {
+        let terms = terms(text);
+        let length = terms.len() as f32;
+
+        let mut frequencies: HashMap<String, f32> = HashMap::new();
+        for term in terms {
+            *frequencies.entry(term).or_default() += 1.0;
+        }
+
+        let normalization = self.k1 * (1.0 - self.b + self.b * length / self.average_length);
+        // Sorted by index, hash collisions are summed
+        let mut weights: BTreeMap<u32, f32> = BTreeMap::new();
+        for (term, frequency) in frequencies {
+            *weights.entry(term_index(&term)).or_default() +=
+                frequency * (self.k1 + 1.0) / (frequency + normalization);
+        }
+
+        SparseVector {
+            indices: weights.keys().copied().collect(),
+            values: weights.values().copied().collect(),
+        }
+    }
+}
+
+#[async_trait]
+impl Transformer for Bm25Embed {
+    #[tracing::instrument(skip_all, name = "transformers.bm25_embed")]
+    async fn transform_node(&self, mut node: IngestionNode) -> Result<IngestionNode> {
+        let sparse_vector = self.sparse_embed(&self.embed_format.format(&node));
+        node.sparse_vectors
+            .insert(self.vector_name.clone(), sparse_vector);
+        Ok(node)
+    }
+
+    fn concurrency(&self) -> Option<usize> {
+        self.concurrency
+    }
+}
+
+/// Splits text into lowercased terms, including identifiers and their parts.
+fn terms(text: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+
+    for word in text
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|word| !word.is_empty())
+    {
+        let parts = identifier_parts(word);
+        if parts.len() > 1 {
+            terms.extend(parts.into_iter().map(str::to_lowercase));
+        }
+        terms.push(word.to_lowercase());
+    }
+
+    terms
+}
+
+/// Splits an identifier on underscores and on lowercase to uppercase changes.
+fn identifier_parts(word: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+
+    for part in word.split('_').filter(|part| !part.is_empty()) {
+        let mut start = 0;
+        let mut previous_lowercase = false;
+        for (index, c) in part.char_indices() {
+            if c.is_uppercase() && previous_lowercase {
+                parts.push(&part[start..index]);
+                start = index;
+            }
+            previous_lowercase = c.is_lowercase() || c.is_numeric();
+        }
+        parts.push(&part[start..]);
+    }
+
+    parts
+}
+
+fn term_index(term: &str) -> u32 {
+    let mut hasher = StableHasher::default();
+    hasher.write(term.as_bytes());
+    let hash = hasher.finish();
+    (hash ^ (hash >> 32)) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_terms_include_identifier_parts() {
+        assert_eq!(
+            terms("let userName = get_user_name();"),
+            vec![
+                "let",
+                "user",
+                "name",
+                "username",
+                "get",
+                "user",
+                "name",
+                "get_user_name"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sparse_embed_weights_repeated_terms_higher() {
+        let bm25 = Bm25Embed::new();
+        let vector = bm25.sparse_embed("parse parse tokens");
+
+        assert_eq!(vector.indices.len(), 2);
+        assert!(vector.indices.windows(2).all(|w| w[0] < w[1]));
+        let weight = |term: &str| {
+            let position = vector.indices.iter().position(|i| *i == term_index(term));
+            vector.values[position.unwrap()]
+        };
+        assert!(weight("parse") > weight("tokens"));
+        assert_eq!(bm25.sparse_embed("parse parse tokens"), vector);
+    }
+
+    #[tokio::test]
+    async fn test_stores_named_sparse_vector() {
+        let node = IngestionNode {
+            chunk: "fn main() {}".to_string(),
+            ..Default::default()
+        };
+
+        let node = Bm25Embed::new()
+            .with_vector_name("sparse")
+            .transform_node(node)
+            .await
+            .unwrap();
+
+        assert_eq!(node.sparse_vectors["sparse"].indices.len(), 2);
+    }
+}
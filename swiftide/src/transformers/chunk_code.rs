@@ -80,19 +80,18 @@ impl ChunkerTransformer for ChunkCode {
     /// - If the code splitting fails, an error is sent downstream.
     #[tracing::instrument(skip_all, name = "transformers.chunk_code")]
     async fn transform_node(&self, node: IngestionNode) -> IngestionStream {
-        let split_result = self.chunker.split(&node.chunk);
+        let split_result = self.chunker.split_with_ranges(&node.chunk);
 
-        if let Ok(split) = split_result {
-            return stream::iter(split.into_iter().map(move |chunk| {
-                Ok(IngestionNode {
-                    chunk,
-                    ..node.clone()
-                })
-            }))
-            .boxed();
-        } else {
+        match split_result {
+            Ok(split) => {
+                let chunks = split
+                    .into_iter()
+                    .map(|(range, _)| Ok(node.with_subchunk(range)))
+                    .collect::<Vec<_>>();
+                stream::iter(chunks).boxed()
+            }
             // Send the error downstream
-            return stream::iter(vec![Err(split_result.unwrap_err())]).boxed();
+            Err(error) => stream::iter(vec![Err(error)]).boxed(),
         }
     }
 
@@ -38,17 +38,11 @@ impl ChunkerTransformer for ChunkMarkdown {
     async fn transform_node(&self, node: IngestionNode) -> IngestionStream {
         let chunks = self
             .chunker
-            .chunks(&node.chunk)
-            .map(|chunk| chunk.to_string())
-            .collect::<Vec<String>>();
+            .chunk_indices(&node.chunk)
+            .map(|(offset, chunk)| Ok(node.with_subchunk(offset..offset + chunk.len())))
+            .collect::<Vec<_>>();
 
-        stream::iter(chunks.into_iter().map(move |chunk| {
-            Ok(IngestionNode {
-                chunk,
-                ..node.clone()
-            })
-        }))
-        .boxed()
+        stream::iter(chunks).boxed()
     }
 
     fn concurrency(&self) -> Option<usize> {
@@ -0,0 +1,479 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{
+    ingestion::{EmbedFormat, IngestionNode, IngestionStream, NodeError},
+    tokenizer::EstimatedTokenizer,
+    BatchableTransformer, Embed, Tokenizer,
+};
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use futures_util::{stream, StreamExt};
+
+/// Determines what happens to nodes with more tokens than the model accepts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TokenLimitPolicy {
+    /// The node is sent to the error sink of the pipeline, the rest of the batch is embedded.
+    #[default]
+    Error,
+    /// The text is truncated to the limit.
+    Truncate,
+    /// The text is split into parts within the limit, which are embedded separately and averaged,
+    /// weighted by their number of tokens. A node with a part that is still over the limit is
+    /// returned as an error.
+    SplitAndPool,
+}
+
+/// A transformer that generates embeddings for batches of nodes with any model implementing the
+/// `Embed` trait, such as the OpenAI integration, a local model or a test double.
+///
+/// Inputs are kept within the token limit of the model with a `TokenLimitPolicy`. The limit is
+/// taken from the model if it is known, or set with `with_max_tokens`. Tokens are counted with the
+/// tokenizer of the model if it provides one, such as cl100k for the OpenAI embedding models.
+/// Otherwise they are counted with an `EstimatedTokenizer`, which can be wrong for code and
+/// non-English text; set the exact tokenizer with `with_tokenizer` if the model has a limit.
+///
+/// # Example
+///
+/// ```ignore
+/// IngestionPipeline::from_loader(FileLoader::new(".").with_extensions(&["rs"]))
+///     .then_in_batch(10, GenericEmbed::new(openai_client.clone()))
+/// ```
+#[derive(Debug)]
+pub struct GenericEmbed {
+    client: Arc<dyn Embed>,
+    concurrency: Option<usize>,
+    embed_format: EmbedFormat,
+    vector_name: Option<String>,
+    tokenizer: Arc<dyn Tokenizer>,
+    max_tokens: Option<usize>,
+    token_limit_policy: TokenLimitPolicy,
+    max_batch_tokens: Option<usize>,
+}
+
+/// A text to embed for the node at `index` in the batch.
+struct Input {
+    index: usize,
+    text: String,
+    tokens: usize,
+}
+
+impl GenericEmbed {
+    /// Creates a new instance of `GenericEmbed`.
+    ///
+    /// # Parameters
+    ///
+    /// * `client` - A model implementing the `Embed` trait.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of `GenericEmbed`.
+    pub fn new(client: impl Embed + 'static) -> Self {
+        let tokenizer = client
+            .tokenizer()
+            .unwrap_or_else(|| Arc::new(EstimatedTokenizer::new()));
+
+        Self {
+            client: Arc::new(client),
+            concurrency: None,
+            embed_format: EmbedFormat::default(),
+            vector_name: None,
+            tokenizer,
+            max_tokens: None,
+            token_limit_policy: TokenLimitPolicy::default(),
+            max_batch_tokens: None,
+        }
+    }
+
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = Some(concurrency);
+        self
+    }
+
+    /// Sets the format of the text that is embedded for each node.
+    ///
+    /// Defaults to all metadata sorted by key, followed by the chunk.
+    /// Use `EmbedFormat::chunk_only()` to embed only the chunk.
+    pub fn with_embed_format(mut self, embed_format: EmbedFormat) -> Self {
+        self.embed_format = embed_format;
+        self
+    }
+
+    /// Stores the embeddings as a named vector in `IngestionNode::vectors`, instead of in
+    /// `IngestionNode::vector`.
+    ///
+    /// This allows storing multiple embeddings per node, i.e. of the chunk only and with its
+    /// metadata, or from two different models.
+    pub fn with_vector_name(mut self, vector_name: impl Into<String>) -> Self {
+        self.vector_name = Some(vector_name.into());
+        self
+    }
+
+    /// Sets the tokenizer used to count and truncate inputs. Defaults to the tokenizer of the model,
+    /// or an `EstimatedTokenizer` if the model does not provide one.
+    pub fn with_tokenizer(mut self, tokenizer: impl Tokenizer + 'static) -> Self {
+        self.tokenizer = Arc::new(tokenizer);
+        self
+    }
+
+    /// Sets the maximum number of tokens of a single input, overriding the limit of the model.
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// Sets what happens to nodes over the token limit. Defaults to `TokenLimitPolicy::Error`.
+    pub fn with_token_limit_policy(mut self, token_limit_policy: TokenLimitPolicy) -> Self {
+        self.token_limit_policy = token_limit_policy;
+        self
+    }
+
+    /// Splits batches into requests with at most this number of tokens in total.
+    pub fn with_max_batch_tokens(mut self, max_batch_tokens: usize) -> Self {
+        self.max_batch_tokens = Some(max_batch_tokens);
+        self
+    }
+
+    /// Converts a text into inputs within the token limit, according to the policy.
+    fn inputs(&self, text: String, max_tokens: Option<usize>) -> Result<Vec<(String, usize)>> {
+        let tokens = self.tokenizer.count_tokens(&text);
+        let Some(max_tokens) = max_tokens.filter(|max_tokens| tokens > *max_tokens) else {
+            return Ok(vec![(text, tokens)]);
+        };
+
+        match self.token_limit_policy {
+            TokenLimitPolicy::Error => {
+                anyhow::bail!("Input has {tokens} tokens, exceeding the limit of {max_tokens}")
+            }
+            TokenLimitPolicy::Truncate => {
+                let truncated = self.tokenizer.truncate(&text, max_tokens);
+                Ok(vec![(
+                    truncated.to_string(),
+                    self.tokenizer.count_tokens(truncated),
+                )])
+            }
+            TokenLimitPolicy::SplitAndPool => {
+                let mut parts = Vec::new();
+                let mut rest = text.as_str();
+                while !rest.is_empty() {
+                    let part = self.tokenizer.truncate(rest, max_tokens.max(1));
+                    anyhow::ensure!(!part.is_empty(), "Tokenizer returned an empty part");
+                    // Tokens do not always split at the same place, so a part can still be over
+                    let tokens = self.tokenizer.count_tokens(part);
+                    anyhow::ensure!(
+                        tokens <= max_tokens,
+                        "Part has {tokens} tokens after splitting, exceeding the limit of {max_tokens}"
+                    );
+                    parts.push((part.to_string(), tokens));
+                    rest = &rest[part.len()..];
+                }
+                Ok(parts)
+            }
+        }
+    }
+
+    /// Groups the inputs into requests within the maximum batch tokens, keeping their order.
+    fn pack(&self, inputs: Vec<Input>) -> Vec<Vec<Input>> {
+        let mut batches: Vec<Vec<Input>> = Vec::new();
+        let mut batch_tokens = 0;
+
+        for input in inputs {
+            let fits = self
+                .max_batch_tokens
+                .is_none_or(|max| batch_tokens + input.tokens <= max);
+            match batches.last_mut() {
+                Some(batch) if fits => {
+                    batch_tokens += input.tokens;
+                    batch.push(input);
+                }
+                _ => {
+                    batch_tokens = input.tokens;
+                    batches.push(vec![input]);
+                }
+            }
+        }
+
+        batches
+    }
+
+    /// Embeds all inputs and pools the embeddings of each node, by node index.
+    async fn embed(&self, inputs: Vec<Input>) -> Result<HashMap<usize, Vec<f32>>> {
+        let mut parts: HashMap<usize, Vec<(Vec<f32>, usize)>> = HashMap::new();
+
+        for batch in self.pack(inputs) {
+            let texts = batch.iter().map(|input| input.text.clone()).collect();
+            let embeddings = self.client.embed(texts).await?;
+            anyhow::ensure!(
+                embeddings.len() == batch.len(),
+                "Expected {} embeddings, got {}",
+                batch.len(),
+                embeddings.len()
+            );
+
+            for (input, embedding) in batch.into_iter().zip(embeddings) {
+                parts
+                    .entry(input.index)
+                    .or_default()
+                    .push((embedding, input.tokens));
+            }
+        }
+
+        Ok(parts
+            .into_iter()
+            .map(|(index, parts)| (index, pool(parts)))
+            .collect())
+    }
+}
+
+/// Averages the embeddings of the parts of a text, weighted by their number of tokens.
+#[allow(clippy::cast_precision_loss)]
+fn pool(mut parts: Vec<(Vec<f32>, usize)>) -> Vec<f32> {
+    if parts.len() == 1 {
+        return parts.remove(0).0;
+    }
+
+    let total_tokens = parts.iter().map(|(_, tokens)| tokens).sum::<usize>();
+    let mut pooled = vec![0.0; parts.first().map_or(0, |(embedding, _)| embedding.len())];
+    for (embedding, tokens) in &parts {
+        let weight = if total_tokens == 0 {
+            1.0 / parts.len() as f32
+        } else {
+            *tokens as f32 / total_tokens as f32
+        };
+        for (pooled, value) in pooled.iter_mut().zip(embedding) {
+            *pooled += value * weight;
+        }
+    }
+    pooled
+}
+
+#[async_trait]
+impl BatchableTransformer for GenericEmbed {
+    /// Transforms a batch of `IngestionNode` objects by generating embeddings for them.
+    ///
+    /// # Parameters
+    ///
+    /// * `nodes` - A vector of `IngestionNode` objects to be transformed.
+    ///
+    /// # Returns
+    ///
+    /// An `IngestionStream` containing the transformed `IngestionNode` objects with their embeddings.
+    ///
+    /// # Errors
+    ///
+    /// Nodes over the token limit with `TokenLimitPolicy::Error` are returned as a `NodeError`, so
+    /// only their source fails. If the embedding process fails, the function returns a stream with
+    /// the error.
+    #[tracing::instrument(skip_all, name = "transformers.embed")]
+    async fn batch_transform(&self, nodes: Vec<IngestionNode>) -> IngestionStream {
+        let max_tokens = self.max_tokens.or_else(|| self.client.max_tokens());
+
+        let mut inputs = Vec::new();
+        let mut errors = HashMap::new();
+        for (index, node) in nodes.iter().enumerate() {
+            match self.inputs(self.embed_format.format(node), max_tokens) {
+                Ok(parts) => inputs.extend(parts.into_iter().map(|(text, tokens)| Input {
+                    index,
+                    text,
+                    tokens,
+                })),
+                Err(error) => {
+                    errors.insert(
+                        index,
+                        NodeError::new(&node.path, error.context("Failed to embed")).into(),
+                    );
+                }
+            }
+        }
+
+        let mut embeddings = match self.embed(inputs).await {
+            Ok(embeddings) => embeddings,
+            Err(error) => return stream::iter(vec![Err(error)]).boxed(),
+        };
+
+        let results = nodes
+            .into_iter()
+            .enumerate()
+            .map(|(index, mut node)| {
+                if let Some(error) = errors.remove(&index) {
+                    return Err(error);
+                }
+                let vector = embeddings
+                    .remove(&index)
+                    .context("Missing embedding for node")?;
+                match &self.vector_name {
+                    Some(name) => {
+                        node.vectors.insert(name.clone(), vector);
+                    }
+                    None => node.vector = Some(vector),
+                }
+                Ok(node)
+            })
+            .collect::<Vec<Result<IngestionNode>>>();
+
+        stream::iter(results).boxed()
+    }
+
+    fn concurrency(&self) -> Option<usize> {
+        self.concurrency
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MockEmbed;
+
+    #[tokio::test]
+    async fn test_embeds_with_any_model() {
+        let mut model = MockEmbed::new();
+        model.expect_tokenizer().returning(|| None);
+        model
+            .expect_embed()
+            .withf(|input| input == &["first".to_string(), "second".to_string()])
+            .returning(|input| Ok(input.iter().map(|i| vec![i.len() as f32]).collect()));
+        model.expect_max_tokens().returning(|| None);
+
+        let nodes = ["first", "second"]
+            .map(|chunk| IngestionNode {
+                chunk: chunk.to_string(),
+                ..Default::default()
+            })
+            .to_vec();
+
+        let embedded = GenericEmbed::new(model)
+            .with_embed_format(EmbedFormat::chunk_only())
+            .with_vector_name("chunk")
+            .batch_transform(nodes)
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        let vectors = embedded
+            .into_iter()
+            .map(|node| node.unwrap().vectors["chunk"].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(vectors, vec![vec![5.0], vec![6.0]]);
+    }
+
+    fn nodes(chunks: &[&str]) -> Vec<IngestionNode> {
+        chunks
+            .iter()
+            .map(|chunk| IngestionNode {
+                chunk: chunk.to_string(),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_routes_oversized_nodes_to_errors() {
+        let mut model = MockEmbed::new();
+        model.expect_tokenizer().returning(|| None);
+        model
+            .expect_embed()
+            .withf(|input| input == &["short".to_string()])
+            .returning(|input| Ok(input.iter().map(|_| vec![1.0]).collect()));
+        model.expect_max_tokens().returning(|| Some(2));
+
+        let results = GenericEmbed::new(model)
+            .with_embed_format(EmbedFormat::chunk_only())
+            .batch_transform(nodes(&["short", "much too long"]))
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].as_ref().unwrap().vector, Some(vec![1.0]));
+        assert!(format!("{:#}", results[1].as_ref().unwrap_err()).contains("exceeding the limit"));
+    }
+
+    #[tokio::test]
+    async fn test_truncates_oversized_nodes() {
+        let mut model = MockEmbed::new();
+        model.expect_tokenizer().returning(|| None);
+        model
+            .expect_embed()
+            .withf(|input| input == &["much too".to_string()])
+            .returning(|input| Ok(input.iter().map(|_| vec![1.0]).collect()));
+
+        let results = GenericEmbed::new(model)
+            .with_embed_format(EmbedFormat::chunk_only())
+            .with_max_tokens(2)
+            .with_token_limit_policy(TokenLimitPolicy::Truncate)
+            .batch_transform(nodes(&["much too long"]))
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(results[0].as_ref().unwrap().vector, Some(vec![1.0]));
+    }
+
+    #[tokio::test]
+    async fn test_splits_pools_and_packs_by_tokens() {
+        let mut model = MockEmbed::new();
+        model.expect_tokenizer().returning(|| None);
+        let mut sequence = mockall::Sequence::new();
+        model
+            .expect_embed()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .withf(|input| input == &["12345678".to_string()])
+            .returning(|_| Ok(vec![vec![1.0, 0.0]]));
+        model
+            .expect_embed()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .withf(|input| input == &["abcd".to_string(), "x".to_string()])
+            .returning(|_| Ok(vec![vec![0.0, 1.0], vec![0.5, 0.5]]));
+
+        let results = GenericEmbed::new(model)
+            .with_embed_format(EmbedFormat::chunk_only())
+            .with_max_tokens(2)
+            .with_token_limit_policy(TokenLimitPolicy::SplitAndPool)
+            .with_max_batch_tokens(2)
+            .batch_transform(nodes(&["12345678abcd", "x"]))
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        let vectors = results
+            .into_iter()
+            .map(|node| node.unwrap().vector.unwrap())
+            .collect::<Vec<_>>();
+        // The first node is split into parts of two and one tokens
+        assert_eq!(vectors, vec![vec![2.0 / 3.0, 1.0 / 3.0], vec![0.5, 0.5]]);
+    }
+    /// Truncates to a character per token, but counts two tokens per character.
+    #[derive(Debug)]
+    struct InconsistentTokenizer;
+
+    impl Tokenizer for InconsistentTokenizer {
+        fn count_tokens(&self, text: &str) -> usize {
+            text.len() * 2
+        }
+
+        fn truncate<'a>(&self, text: &'a str, max_tokens: usize) -> &'a str {
+            &text[..max_tokens.min(text.len())]
+        }
+    }
+
+    #[tokio::test]
+    async fn test_routes_nodes_with_oversized_parts_to_errors() {
+        let mut model = MockEmbed::new();
+        model.expect_tokenizer().returning(|| None);
+        model.expect_embed().never();
+
+        let results = GenericEmbed::new(model)
+            .with_embed_format(EmbedFormat::chunk_only())
+            .with_tokenizer(InconsistentTokenizer)
+            .with_max_tokens(2)
+            .with_token_limit_policy(TokenLimitPolicy::SplitAndPool)
+            .batch_transform(nodes(&["abcd"]))
+            .await
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(results.len(), 1);
+        assert!(format!("{:#}", results[0].as_ref().unwrap_err()).contains("after splitting"));
+    }
+}
@@ -113,7 +113,7 @@ impl Transformer for MetadataQACode {
         let response = self.client.prompt(&prompt).await?;
 
         node.metadata
-            .insert("Questions and Answers".to_string(), response);
+            .insert("Questions and Answers".to_string(), response.into());
 
         Ok(node)
     }
@@ -1,3 +1,7 @@
+//! This module defines the `MetadataQAText` struct and its associated methods,
+//! which are used for generating metadata in the form of questions and answers
+//! from a given text. It interacts with a client (e.g., OpenAI) to generate
+//! these questions and answers based on the text chunk in an `IngestionNode`.
 use std::sync::Arc;
 
 use crate::{ingestion::IngestionNode, SimplePrompt, Transformer};
@@ -5,11 +9,6 @@ use anyhow::Result;
 use async_trait::async_trait;
 use indoc::indoc;
 
-/// This module defines the `MetadataQAText` struct and its associated methods,
-/// which are used for generating metadata in the form of questions and answers
-/// from a given text. It interacts with a client (e.g., OpenAI) to generate
-/// these questions and answers based on the text chunk in an `IngestionNode`.
-
 /// `MetadataQAText` is responsible for generating questions and answers
 /// from a given text chunk. It uses a templated prompt to interact with a client
 /// that implements the `SimplePrompt` trait.
@@ -114,7 +113,7 @@ impl Transformer for MetadataQAText {
         let response = self.client.prompt(&prompt).await?;
 
         node.metadata
-            .insert("Questions and Answers".to_string(), response);
+            .insert("Questions and Answers".to_string(), response.into());
 
         Ok(node)
     }
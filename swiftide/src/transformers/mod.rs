@@ -1,11 +1,16 @@
+pub mod bm25_embed;
 pub mod chunk_code;
 pub mod chunk_markdown;
+pub mod embed;
 pub mod metadata_qa_code;
 pub mod metadata_qa_text;
 pub mod openai_embed;
 
+pub use bm25_embed::Bm25Embed;
 pub use chunk_code::ChunkCode;
 pub use chunk_markdown::ChunkMarkdown;
+pub use embed::{GenericEmbed, TokenLimitPolicy};
 pub use metadata_qa_code::MetadataQACode;
 pub use metadata_qa_text::MetadataQAText;
+#[allow(deprecated)]
 pub use openai_embed::OpenAIEmbed;
@@ -0,0 +1,167 @@
+//! This module contains tests for the Anthropic integration in the Swiftide project.
+//! The tests simulate the Messages API with a mock server and validate that prompts are sent and
+//! that responses and errors are mapped correctly.
+
+use std::time::{Duration, Instant};
+
+use serde_json::json;
+use swiftide::{
+    ingestion::{IngestionNode, Retry, RetryPolicy},
+    integrations::anthropic::{self, Anthropic, AnthropicError},
+    transformers::MetadataQAText,
+    SimplePrompt, Transformer,
+};
+use wiremock::matchers::{body_json, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn anthropic(mock_server: &MockServer) -> Anthropic {
+    Anthropic::builder()
+        .base_url(mock_server.uri())
+        .api_key("test-key")
+        .default_prompt_model("claude-3-haiku-20240307")
+        .default_max_tokens(512)
+        .default_system_prompt("Answer briefly.")
+        .build()
+        .unwrap()
+}
+
+/// Tests that a prompt is sent as a single user message with the model, max tokens and system prompt.
+#[test_log::test(tokio::test)]
+async fn test_prompt() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .and(header("x-api-key", "test-key"))
+        .and(header("anthropic-version", "2023-06-01"))
+        .and(body_json(json!({
+            "model": "claude-3-haiku-20240307",
+            "max_tokens": 512,
+            "system": "Answer briefly.",
+            "messages": [{ "role": "user", "content": "Hello?" }]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "msg_01",
+            "type": "message",
+            "role": "assistant",
+            "model": "claude-3-haiku-20240307",
+            "content": [
+                { "type": "text", "text": "Hello there, " },
+                { "type": "text", "text": "how may I assist you today?" }
+            ],
+            "stop_reason": "end_turn",
+            "usage": { "input_tokens": 10, "output_tokens": 12 }
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let response = anthropic(&mock_server).prompt("Hello?").await.unwrap();
+
+    assert_eq!(response, "Hello there, how may I assist you today?");
+}
+
+/// Tests that an overloaded API returns a retryable `AnthropicError`.
+#[test_log::test(tokio::test)]
+async fn test_overloaded_is_retryable() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(
+            ResponseTemplate::new(529)
+                .insert_header("retry-after", "3")
+                .set_body_json(json!({
+                    "type": "error",
+                    "error": { "type": "overloaded_error", "message": "Overloaded" }
+                })),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let error = anthropic(&mock_server).prompt("Hello?").await.unwrap_err();
+
+    assert!(anthropic::is_retryable(&error));
+    let error = error.downcast_ref::<AnthropicError>().unwrap();
+    assert_eq!(error.status, 529);
+    assert_eq!(error.kind, "overloaded_error");
+    assert_eq!(error.retry_after, Some(Duration::from_secs(3)));
+}
+
+/// Tests that an invalid request is not retried and keeps the message of the API.
+#[test_log::test(tokio::test)]
+async fn test_invalid_request_is_not_retryable() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "type": "error",
+            "error": {
+                "type": "invalid_request_error",
+                "message": "max_tokens: must be greater than 0"
+            }
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let error = anthropic(&mock_server).prompt("Hello?").await.unwrap_err();
+
+    assert!(!anthropic::is_retryable(&error));
+    assert!(error
+        .to_string()
+        .contains("max_tokens: must be greater than 0"));
+}
+
+/// Tests that a retried prompt waits as long as the `retry-after` header asks, instead of only the
+/// backoff of the policy.
+#[test_log::test(tokio::test)]
+async fn test_retry_waits_for_retry_after() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(
+            ResponseTemplate::new(429)
+                .insert_header("retry-after", "1")
+                .set_body_json(json!({
+                    "type": "error",
+                    "error": { "type": "rate_limit_error", "message": "Rate limited" }
+                })),
+        )
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "content": [{ "type": "text", "text": "Q1: What? A1: This." }],
+            "stop_reason": "end_turn"
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let policy = RetryPolicy::default()
+        .with_initial_backoff(Duration::from_millis(1))
+        .with_jitter(false)
+        .with_retryable(anthropic::is_retryable)
+        .with_retry_after(anthropic::retry_after);
+    let retry = Retry::new(MetadataQAText::new(anthropic(&mock_server)), policy);
+
+    let start = Instant::now();
+    let node = retry
+        .transform_node(IngestionNode {
+            chunk: "Some text".to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    assert!(start.elapsed() >= Duration::from_secs(1));
+    assert_eq!(
+        node.metadata["Questions and Answers"],
+        "Q1: What? A1: This."
+    );
+}
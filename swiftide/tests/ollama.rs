@@ -0,0 +1,97 @@
+//! This module contains tests for the Ollama integration in the Swiftide project.
+//! The tests simulate the Ollama HTTP API with a mock server and validate that prompts and
+//! embeddings are sent and parsed correctly.
+
+use serde_json::json;
+use swiftide::{integrations::ollama::Ollama, Embed, SimplePrompt};
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn ollama(mock_server: &MockServer) -> Ollama {
+    Ollama::builder()
+        .base_url(mock_server.uri())
+        .default_prompt_model("llama3")
+        .default_embed_model("nomic-embed-text")
+        .build()
+        .unwrap()
+}
+
+/// Tests that a prompt is sent as a single user message to the chat endpoint.
+#[test_log::test(tokio::test)]
+async fn test_prompt() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/api/chat"))
+        .and(body_partial_json(json!({
+            "model": "llama3",
+            "messages": [{ "role": "user", "content": "Hello?" }],
+            "stream": false
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "model": "llama3",
+            "created_at": "2024-06-13T09:42:00.000000Z",
+            "message": {
+                "role": "assistant",
+                "content": "Hello there, how may I assist you today?"
+            },
+            "done": true
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let response = ollama(&mock_server).await.prompt("Hello?").await.unwrap();
+
+    assert_eq!(response, "Hello there, how may I assist you today?");
+}
+
+/// Tests that all inputs are embedded with a single request.
+#[test_log::test(tokio::test)]
+async fn test_embed() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/api/embed"))
+        .and(body_partial_json(json!({
+            "model": "nomic-embed-text",
+            "input": ["first", "second"]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "model": "nomic-embed-text",
+            "embeddings": [[0.1, 0.2], [0.3, 0.4]]
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let embeddings = ollama(&mock_server)
+        .await
+        .embed(vec!["first".to_string(), "second".to_string()])
+        .await
+        .unwrap();
+
+    assert_eq!(embeddings, vec![vec![0.1, 0.2], vec![0.3, 0.4]]);
+}
+
+/// Tests that errors from Ollama, such as a missing model, are returned with their message.
+#[test_log::test(tokio::test)]
+async fn test_error_response() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/api/embed"))
+        .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+            "error": "model \"nomic-embed-text\" not found, try pulling it first"
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let error = ollama(&mock_server)
+        .await
+        .embed(vec!["first".to_string()])
+        .await
+        .unwrap_err();
+
+    assert!(error.to_string().contains("try pulling it first"));
+}
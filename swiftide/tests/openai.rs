@@ -0,0 +1,126 @@
+//! This module contains tests for configuring the OpenAI integration in the Swiftide project.
+//! The tests simulate OpenAI compatible servers and Azure OpenAI with a mock server and validate
+//! that requests are sent to the configured URL with the configured headers.
+
+use std::time::Duration;
+
+use serde_json::json;
+use swiftide::{integrations::openai::OpenAI, Embed};
+use wiremock::matchers::{header, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn embeddings_response() -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({
+      "object": "list",
+      "data": [{ "object": "embedding", "embedding": [0.5, 0.5], "index": 0 }],
+      "model": "text-embedding-3-small",
+      "usage": { "prompt_tokens": 1, "total_tokens": 1 }
+    }))
+}
+
+/// Tests that requests go to a custom base URL with the api key, organization and custom headers.
+#[test_log::test(tokio::test)]
+async fn test_compatible_server_with_headers() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/embeddings"))
+        .and(header("authorization", "Bearer secret"))
+        .and(header("openai-organization", "org-123"))
+        .and(header("x-team", "search"))
+        .respond_with(embeddings_response())
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let openai = OpenAI::builder()
+        .api_base(format!("{}/v1", mock_server.uri()))
+        .api_key("secret")
+        .organization("org-123")
+        .header("X-Team", "search")
+        .default_embed_model("text-embedding-3-small")
+        .build()
+        .unwrap();
+
+    let embeddings = openai.embed(vec!["hello".to_string()]).await.unwrap();
+
+    assert_eq!(embeddings, vec![vec![0.5, 0.5]]);
+}
+
+/// Tests that Azure OpenAI requests go to the deployment with the api version and api key header.
+#[test_log::test(tokio::test)]
+async fn test_azure_deployment() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/openai/deployments/embeddings-small/embeddings"))
+        .and(query_param("api-version", "2024-02-01"))
+        .and(header("api-key", "secret"))
+        .respond_with(embeddings_response())
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let openai = OpenAI::builder()
+        .api_base(mock_server.uri())
+        .api_key("secret")
+        .azure_deployment("embeddings-small", "2024-02-01")
+        .default_embed_model("text-embedding-3-small")
+        .build()
+        .unwrap();
+
+    let embeddings = openai.embed(vec!["hello".to_string()]).await.unwrap();
+
+    assert_eq!(embeddings, vec![vec![0.5, 0.5]]);
+}
+
+/// Tests that slow requests fail with the configured timeout.
+#[test_log::test(tokio::test)]
+async fn test_timeout() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/embeddings"))
+        .respond_with(embeddings_response().set_delay(Duration::from_secs(5)))
+        .mount(&mock_server)
+        .await;
+
+    let openai = OpenAI::builder()
+        .api_base(mock_server.uri())
+        .timeout(Duration::from_millis(100))
+        .default_embed_model("text-embedding-3-small")
+        .build()
+        .unwrap();
+
+    assert!(openai.embed(vec!["hello".to_string()]).await.is_err());
+}
+
+/// Tests that invalid headers fail the build instead of the requests.
+#[test]
+fn test_invalid_header() {
+    assert!(OpenAI::builder()
+        .header("X-Team", "line\nbreak")
+        .build()
+        .is_err());
+}
+
+/// Tests that a client cannot be combined with options it would silently ignore.
+#[test]
+fn test_client_with_other_options() {
+    let client = async_openai::Client::new;
+
+    let error = OpenAI::builder()
+        .client(client())
+        .api_base("http://localhost:8000/v1")
+        .header("X-Team", "search")
+        .build()
+        .unwrap_err();
+    assert!(error.to_string().contains("api_base, header"));
+
+    assert!(OpenAI::builder()
+        .client(client())
+        .azure_deployment("embeddings", "2024-02-01")
+        .build()
+        .is_err());
+    assert!(OpenAI::builder().client(client()).build().is_ok());
+}